@@ -1,6 +1,7 @@
 #![deny(warnings)]
 extern crate path_absolutize;
 
+use crate::lock;
 use eyre::Report;
 use inquire::Confirm;
 use path_absolutize::*;
@@ -51,11 +52,26 @@ pub fn new(gpm_rc_file_path: &Path) -> Result<Configure, Report> {
 }
 
 impl Configure {
-    fn update_file(&self) -> Result<(), Report> {
+    // Update the roots based on the latest file with lock, the changes of other gpm are kept
+    fn update_file<F>(&mut self, update: F) -> Result<(), Report>
+    where
+        F: FnOnce(&mut Vec<String>),
+    {
+        let file_path = Path::new(&self.file_path).to_path_buf();
+
+        let _lock = lock::lock(&lock::lock_path(&file_path))?;
+
+        // the file broken by others is not overwritten by the stale roots
+        let latest = new(&file_path)?;
+
+        self.root = latest.root;
+
+        update(&mut self.root);
+
         let serialized = serde_json::to_string(&self).unwrap();
 
-        fs::write(Path::new(&self.file_path), serialized)
-            .unwrap_or_else(|e| panic!("can not write to '{}': {:?}", &self.file_path, e));
+        lock::write_atomic(&file_path, &serialized)?;
+
         Ok(())
     }
 
@@ -102,23 +118,22 @@ impl Configure {
                     .unwrap()
                     .to_string();
 
-                if !self.root.contains(new_roo_str) {
-                    println!("Added '{}' to root of configure.", new_roo_str);
-                    self.root.push(new_roo_str.to_owned());
-                }
-
-                Option::Some(true)
+                Option::Some(new_roo_str.to_owned())
             }
             _ => Option::None,
         };
 
-        if result.is_none() {
-            Err(Report::msg(format!(
+        match result {
+            None => Err(Report::msg(format!(
                 "unknown field '{}' of configure",
                 field
-            )))
-        } else {
-            self.update_file()
+            ))),
+            Some(new_root) => self.update_file(|root| {
+                if !root.contains(&new_root) {
+                    println!("Added '{}' to root of configure.", new_root);
+                    root.push(new_root);
+                }
+            }),
         }
     }
 
@@ -167,28 +182,23 @@ impl Configure {
 
                 println!("Set '[{}]' to root of configure.", new_roo_str);
 
-                self.root = vec![new_roo_str.to_owned()];
-                Option::Some(true)
+                Option::Some(new_roo_str.to_owned())
             }
             _ => Option::None,
         };
 
-        if result.is_none() {
-            Err(Report::msg(format!(
+        match result {
+            None => Err(Report::msg(format!(
                 "unknown field '{}' of configure",
                 field
-            )))
-        } else {
-            self.update_file()
+            ))),
+            Some(new_root) => self.update_file(|root| *root = vec![new_root]),
         }
     }
 
     pub fn remove_field(&mut self, field: &str) -> Result<(), Report> {
         let result = match field {
-            "root" => {
-                self.root = vec![];
-                Option::Some(true)
-            }
+            "root" => Option::Some(true),
             _ => Option::None,
         };
 
@@ -198,14 +208,12 @@ impl Configure {
                 field
             )))
         } else {
-            self.update_file()
+            self.update_file(|root| root.clear())
         }
     }
 
     pub fn reset(&mut self) -> Result<(), Report> {
-        self.root = vec![];
-
-        self.update_file()
+        self.update_file(|root| root.clear())
     }
 }
 
@@ -218,9 +226,10 @@ impl fmt::Display for Configure {
 
 #[cfg(test)]
 mod tests {
-    use std::env;
+    use std::{env, fs};
 
     use crate::configure;
+    use crate::testing;
 
     #[test]
     fn test_empty_configure() {
@@ -408,4 +417,33 @@ mod tests {
             config.add_field("root", "/path/to/a/dir/b", true).unwrap();
         }
     }
+
+    #[test]
+    fn test_configure_concurrent_update() {
+        let dir = testing::temp_dir("configure-concurrent");
+        let gpm_rc = dir.join(".gpmrc");
+
+        fs::write(&gpm_rc, r#"{"root":[]}"#).unwrap();
+
+        // the configures are loaded by two gpm at the same time
+        let mut c1 = configure::new(&gpm_rc).unwrap();
+        let mut c2 = configure::new(&gpm_rc).unwrap();
+
+        c1.add_field("root", "/path/to/a", true).unwrap();
+        c2.add_field("root", "/path/to/b", true).unwrap();
+
+        let rc = configure::new(&gpm_rc).unwrap();
+
+        assert_eq!(rc.root.len(), 2);
+        assert_eq!(c2.root, rc.root);
+        assert!(dir.join(".gpmrc.lock").exists());
+
+        // the file is broken after loaded
+        fs::write(&gpm_rc, "{").unwrap();
+
+        assert!(c1.add_field("root", "/path/to/c", true).is_err());
+        assert_eq!(fs::read_to_string(&gpm_rc).unwrap(), "{");
+
+        fs::remove_dir_all(dir).unwrap();
+    }
 }
@@ -0,0 +1,402 @@
+#![deny(warnings)]
+
+use crate::format::Output;
+use crate::git;
+use crate::parallel;
+use crate::remote::RemoteUrl;
+use crate::remove::{self, Loss};
+use crate::repository::Repository;
+use eyre::Report;
+use serde_json::json;
+use std::collections::HashMap;
+use std::path::Path;
+
+// The remote which does not depend on the protocol, the user and the case.
+// eg. git@github.com:Axetroy/gpm.rs.git and https://github.com/axetroy/gpm.rs are the same
+pub fn canonical_remote(url: &str) -> String {
+    match RemoteUrl::parse(url) {
+        Some(remote) => {
+            let path = remote.path.trim_end_matches('/');
+            let path = path.strip_suffix(".git").unwrap_or(path);
+
+            format!("{}/{}", remote.host, path).to_lowercase()
+        }
+        None => url.trim_end_matches('/').to_string(),
+    }
+}
+
+// A clone of the duplicated repository
+#[derive(Debug, Clone, PartialEq)]
+pub struct Member {
+    pub repo: Repository,
+    pub origin: String,
+    pub branch: Option<String>,
+    // the short hash of HEAD
+    pub head: Option<String>,
+    // the date (ISO 8601) of the last commit
+    pub last_commit: Option<String>,
+    // the work which would be lost if the clone is removed
+    pub loss: Loss,
+}
+
+impl Member {
+    pub fn check(repo: Repository, origin: String) -> Member {
+        let dir = repo.path.as_path();
+
+        Member {
+            branch: git::current_branch(dir).ok().flatten(),
+            head: git::output(dir, &["rev-parse", "--short", "HEAD"]).ok(),
+            last_commit: git::last_commit(dir).ok().flatten().map(|(date, _)| date),
+            loss: Loss::check(dir),
+            origin,
+            repo,
+        }
+    }
+
+    // Whether the clone is at the location computed from the origin
+    pub fn is_in_place(&self) -> bool {
+        git::url_to_path(&self.repo.root, &self.origin)
+            .ok()
+            .as_ref()
+            == Some(&self.repo.path)
+    }
+
+    // eg. 'main 1a2b3c4 2024-01-01T00:00:00+08:00, dirty, 2 unpushed'
+    pub fn summary(&self) -> String {
+        let mut parts = vec![format!(
+            "{} {} {}",
+            self.branch.as_deref().unwrap_or("(detached)"),
+            self.head.as_deref().unwrap_or("-"),
+            self.last_commit.as_deref().unwrap_or("-")
+        )];
+
+        if !self.loss.changes.is_empty() || !self.loss.untracked.is_empty() {
+            parts.push("dirty".to_string());
+        }
+
+        if !self.loss.stashes.is_empty() {
+            parts.push(format!("{} stashed", self.loss.stashes.len()));
+        }
+
+        let unpushed = self.loss.unpushed.iter().map(|(_, c)| c).sum::<u32>();
+
+        if unpushed > 0 {
+            parts.push(format!("{} unpushed", unpushed));
+        }
+
+        parts.join(", ")
+    }
+}
+
+// The clones of the same remote
+#[derive(Debug, Clone, PartialEq)]
+pub struct Group {
+    pub remote: String,
+    pub members: Vec<Member>,
+    // the index of the clone to keep, the others are the extras
+    pub keep: usize,
+}
+
+// Group the clones by the canonical remote of origin, the repositories without origin are ignored
+pub fn find(repositories: Vec<Repository>) -> Vec<Group> {
+    let origins = parallel::map(&repositories, parallel::default_jobs(), |r| {
+        git::remote_url(&r.path, "origin")
+    });
+
+    let mut groups: Vec<(String, Vec<(Repository, String)>)> = vec![];
+    // the index of group by the canonical remote, the groups keep the order of repositories
+    let mut indexes: HashMap<String, usize> = HashMap::new();
+
+    for (repo, origin) in repositories.into_iter().zip(origins) {
+        let Some(origin) = origin else {
+            continue;
+        };
+
+        let remote = canonical_remote(&origin);
+
+        match indexes.get(&remote) {
+            Some(&i) => groups[i].1.push((repo, origin)),
+            None => {
+                indexes.insert(remote.clone(), groups.len());
+                groups.push((remote, vec![(repo, origin)]));
+            }
+        }
+    }
+
+    groups
+        .into_iter()
+        .filter(|(_, clones)| clones.len() > 1)
+        .map(|(remote, clones)| {
+            let members = parallel::map(&clones, parallel::default_jobs(), |(repo, origin)| {
+                Member::check(repo.clone(), origin.clone())
+            });
+
+            // keep the clone in place, or the first one
+            let keep = members.iter().position(|m| m.is_in_place()).unwrap_or(0);
+
+            Group {
+                remote,
+                members,
+                keep,
+            }
+        })
+        .collect()
+}
+
+// The branch of keeper to receive the branch of extra, the existing branch is never overwritten
+fn merge_target(keeper: &Path, extra: &Member, branch: &str) -> String {
+    let exists = |b: &str| git::rev_parse(keeper, &format!("refs/heads/{}", b)).is_some();
+
+    if !exists(branch) {
+        return branch.to_string();
+    }
+
+    // eg. gpm.rs(1) -> gpm.rs-1
+    let suffix = extra
+        .repo
+        .name
+        .chars()
+        .map(|c| {
+            if c.is_ascii_alphanumeric() || c == '.' {
+                c
+            } else {
+                '-'
+            }
+        })
+        .collect::<String>();
+
+    let suffix = suffix.trim_matches('-');
+
+    let mut target = format!("{}-{}", branch, suffix);
+    let mut index = 1;
+
+    while exists(&target) {
+        index += 1;
+        target = format!("{}-{}-{}", branch, suffix, index);
+    }
+
+    target
+}
+
+// Fetch the unpushed branches of extra into the keeper, return the merged branches. eg. 'dev -> dev-gpm.rs-1'
+pub fn merge_branches(
+    keeper: &Member,
+    extra: &Member,
+    dry_run: bool,
+) -> Result<Vec<String>, Report> {
+    let mut merged: Vec<String> = vec![];
+
+    for (branch, _) in &extra.loss.unpushed {
+        // the commits may be in the keeper already
+        if let Some(tip) = git::rev_parse(&extra.repo.path, &format!("refs/heads/{}", branch)) {
+            let contained =
+                git::output(&keeper.repo.path, &["branch", "--contains", &tip]).unwrap_or_default();
+
+            if !contained.is_empty() {
+                continue;
+            }
+        }
+
+        let target = merge_target(&keeper.repo.path, extra, branch);
+
+        if !dry_run {
+            git::output(
+                &keeper.repo.path,
+                &[
+                    "fetch",
+                    "--quiet",
+                    extra.repo.path_str(),
+                    &format!("refs/heads/{}:refs/heads/{}", branch, target),
+                ],
+            )?;
+        }
+
+        merged.push(format!("{} -> {}", branch, target));
+    }
+
+    Ok(merged)
+}
+
+// What is done to the extra clone
+#[derive(Debug, Clone, PartialEq)]
+pub struct Resolution {
+    pub result: String,
+    pub merged: Vec<String>,
+}
+
+// Merge the unpushed branches of extra into the keeper, then move the extra to trash.
+// The extra with uncommitted changes, untracked files or stashes is refused unless forced.
+pub fn resolve(keeper: &Member, extra: &Member, force: bool, dry_run: bool) -> Resolution {
+    let loss = &extra.loss;
+
+    let blocked = loss.error.is_some()
+        || !loss.changes.is_empty()
+        || !loss.untracked.is_empty()
+        || !loss.stashes.is_empty();
+
+    if blocked && !force {
+        return Resolution {
+            result: "refused".to_string(),
+            merged: vec![],
+        };
+    }
+
+    let merged = match merge_branches(keeper, extra, dry_run) {
+        Ok(merged) => merged,
+        Err(e) => {
+            return Resolution {
+                result: format!("failed: can not merge the branches: {}", e),
+                merged: vec![],
+            }
+        }
+    };
+
+    let result = if dry_run {
+        "would-remove".to_string()
+    } else {
+        match remove::remove(&extra.repo) {
+            Ok(_) => "removed".to_string(),
+            Err(e) => format!("failed: {}", e),
+        }
+    };
+
+    Resolution { result, merged }
+}
+
+// The structured output of duplicates, the result of the keeper is 'kept'
+pub fn output(groups: &[Group], resolutions: &[Vec<Option<Resolution>>]) -> Output {
+    let fields = Repository::FIELDS
+        .into_iter()
+        .chain([
+            "remote",
+            "keep",
+            "branch",
+            "head",
+            "last_commit",
+            "loss",
+            "result",
+            "merged",
+        ])
+        .collect::<Vec<&str>>();
+
+    let mut output = Output::new("dedupe", &fields).with_tree_levels(3);
+
+    for (group, resolutions) in groups.iter().zip(resolutions) {
+        for (i, (member, resolution)) in group.members.iter().zip(resolutions).enumerate() {
+            let mut record = member.repo.fields();
+
+            let (result, merged) = match resolution {
+                Some(r) => (Some(r.result.clone()), r.merged.clone()),
+                None if i == group.keep => (Some("kept".to_string()), vec![]),
+                None => (None, vec![]),
+            };
+
+            record.extend([
+                json!(group.remote),
+                json!(i == group.keep),
+                json!(member.branch),
+                json!(member.head),
+                json!(member.last_commit),
+                json!(member.loss.lines()),
+                json!(result),
+                json!(merged),
+            ]);
+
+            output.push(record);
+        }
+    }
+
+    output
+}
+
+#[cfg(test)]
+mod tests {
+    use std::fs;
+
+    use crate::dedupe;
+    use crate::git;
+    use crate::repository::Repository;
+    use crate::testing;
+
+    #[test]
+    fn test_canonical_remote() {
+        for url in [
+            "https://github.com/axetroy/gpm.rs",
+            "https://github.com/Axetroy/gpm.rs.git",
+            "git@github.com:axetroy/gpm.rs.git",
+            "ssh://git@github.com:22/axetroy/gpm.rs/",
+        ] {
+            assert_eq!(
+                dedupe::canonical_remote(url),
+                "github.com/axetroy/gpm.rs",
+                "{}",
+                url
+            );
+        }
+    }
+
+    #[test]
+    fn test_find_and_resolve() {
+        let root = testing::temp_dir("dedupe-root");
+        let root_str = root.to_str().unwrap();
+
+        let owner = root.join("github.com").join("axetroy");
+        let a = owner.join("gpm.rs");
+        let b = owner.join("gpm.rs(1)");
+        let c = owner.join("gpm.rs(2)");
+        let d = owner.join("cask.rs");
+
+        for (dir, remote) in [
+            (&b, "git@github.com:axetroy/gpm.rs.git"),
+            (&a, "https://github.com/axetroy/gpm.rs"),
+            (&c, "https://github.com/axetroy/gpm.rs"),
+            (&d, "https://github.com/axetroy/cask.rs"),
+        ] {
+            fs::create_dir_all(dir).unwrap();
+            testing::git(dir, &["init", "-q", "-b", "main"]);
+            testing::git(dir, &["remote", "add", "origin", remote]);
+            testing::commit(dir, "init");
+        }
+
+        testing::commit(&b, "work");
+        fs::write(c.join("untracked"), "").unwrap();
+
+        let repositories = [&b, &a, &c, &d]
+            .iter()
+            .map(|p| Repository::new(root_str, p.to_path_buf()))
+            .collect::<Vec<Repository>>();
+
+        let groups = dedupe::find(repositories);
+
+        assert_eq!(groups.len(), 1);
+        assert_eq!(groups[0].members.len(), 3);
+        // the clone in place is kept
+        assert_eq!(groups[0].keep, 1);
+
+        let keeper = &groups[0].members[1];
+        let extras = [&groups[0].members[0], &groups[0].members[2]];
+
+        assert_eq!(extras[0].repo.path, b);
+        assert_eq!(extras[0].loss.unpushed, vec![("main".to_string(), 2)]);
+
+        let resolution = dedupe::resolve(keeper, extras[0], false, true);
+
+        assert_eq!(resolution.result, "would-remove");
+        assert_eq!(resolution.merged, vec!["main -> main-gpm.rs-1"]);
+        assert!(b.exists());
+
+        let resolution = dedupe::resolve(keeper, extras[0], false, false);
+
+        assert_eq!(resolution.result, "removed");
+        assert!(!b.exists());
+        assert!(git::rev_parse(&a, "refs/heads/main-gpm.rs-1").is_some());
+
+        assert_eq!(
+            dedupe::resolve(keeper, extras[1], false, false).result,
+            "refused"
+        );
+        assert!(c.exists());
+
+        fs::remove_dir_all(root).unwrap();
+    }
+}
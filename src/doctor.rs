@@ -0,0 +1,720 @@
+#![deny(warnings)]
+
+use crate::dedupe;
+use crate::file_explorer;
+use crate::format::Output;
+use crate::git;
+use crate::relocate;
+use crate::remove;
+use crate::repository::Repository;
+use crate::staging;
+use crate::vscode;
+use eyre::Report;
+use serde_json::{json, Value};
+use std::fs::{self, OpenOptions};
+use std::path::{Path, PathBuf};
+use std::process::{self, Command as ChildProcess};
+
+// 'git remote get-url' is added in 2.7
+const MIN_GIT_VERSION: (u32, u32) = (2, 7);
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Level {
+    Ok,
+    Warning,
+    Error,
+}
+
+impl Level {
+    pub fn name(&self) -> &'static str {
+        match self {
+            Level::Ok => "ok",
+            Level::Warning => "warning",
+            Level::Error => "error",
+        }
+    }
+}
+
+// The safe fix which is applied by --fix, the others need the decision of user. eg. relocate
+#[derive(Debug, Clone, PartialEq)]
+pub enum Fix {
+    CreateRoot(PathBuf),
+    CleanStaging(String),
+    RemoveEmptyFolder { root: String, path: PathBuf },
+}
+
+impl Fix {
+    pub fn apply(&self) -> Result<(), Report> {
+        match self {
+            Fix::CreateRoot(path) => Ok(fs::create_dir_all(path)?),
+            Fix::CleanStaging(root) => staging::clean(root).map(|_| ()),
+            Fix::RemoveEmptyFolder { root, path } => {
+                fs::remove_dir(path)?;
+                remove::prune_empty_parents(Path::new(root), path)
+            }
+        }
+    }
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct Finding {
+    // what is checked. eg. git, config, root, opener, layout
+    pub check: &'static str,
+    pub level: Level,
+    pub message: String,
+    // how to fix it by hand
+    pub suggestion: Option<String>,
+    pub fix: Option<Fix>,
+}
+
+impl Finding {
+    fn new(check: &'static str, level: Level, message: String) -> Finding {
+        Finding {
+            check,
+            level,
+            message,
+            suggestion: None,
+            fix: None,
+        }
+    }
+
+    fn suggest(mut self, suggestion: &str) -> Finding {
+        self.suggestion = Some(suggestion.to_string());
+        self
+    }
+
+    fn fix(mut self, fix: Fix) -> Finding {
+        self.fix = Some(fix);
+        self
+    }
+}
+
+// Parse the version of 'git --version'. eg. 'git version 2.39.3 (Apple Git-145)' -> (2, 39)
+fn parse_git_version(output: &str) -> Option<(u32, u32)> {
+    let version = output.split_whitespace().nth(2)?;
+    let mut parts = version.split('.');
+
+    let major = parts.next()?.parse::<u32>().ok()?;
+    let minor = parts.next()?.parse::<u32>().ok()?;
+
+    Some((major, minor))
+}
+
+pub fn check_git() -> Finding {
+    let output = match ChildProcess::new("git").arg("--version").output() {
+        Ok(output) if output.status.success() => output,
+        _ => {
+            return Finding::new("git", Level::Error, "git is not found".to_string())
+                .suggest("install git and add it to PATH, https://git-scm.com/downloads")
+        }
+    };
+
+    let version = String::from_utf8_lossy(&output.stdout).trim().to_string();
+
+    match parse_git_version(&version) {
+        Some(v) if v < MIN_GIT_VERSION => Finding::new(
+            "git",
+            Level::Warning,
+            format!(
+                "{}, {}.{} or later is required",
+                version, MIN_GIT_VERSION.0, MIN_GIT_VERSION.1
+            ),
+        )
+        .suggest("upgrade git"),
+        Some(_) => Finding::new("git", Level::Ok, version),
+        None => Finding::new(
+            "git",
+            Level::Warning,
+            format!("unknown version '{}'", version),
+        ),
+    }
+}
+
+// Check the configure file against the schema: { "root": ["<absolute path>", ...] }
+pub fn check_config(path: &Path) -> Vec<Finding> {
+    let display = path.display();
+
+    let content = match fs::read_to_string(path) {
+        Ok(content) => content,
+        Err(e) => {
+            return vec![Finding::new(
+                "config",
+                Level::Error,
+                format!("can not read '{}': {}", display, e),
+            )]
+        }
+    };
+
+    let reset = "fix the JSON by hand, or remove it and run 'gpm config add root <folder>'";
+
+    let value = match serde_json::from_str::<Value>(&content) {
+        Ok(value) => value,
+        Err(e) => {
+            return vec![Finding::new(
+                "config",
+                Level::Error,
+                format!("'{}' is not valid JSON: {}", display, e),
+            )
+            .suggest(reset)]
+        }
+    };
+
+    let Some(object) = value.as_object() else {
+        return vec![Finding::new(
+            "config",
+            Level::Error,
+            format!("'{}' is not a JSON object", display),
+        )
+        .suggest(reset)];
+    };
+
+    let mut findings: Vec<Finding> = vec![];
+
+    for key in object.keys().filter(|k| *k != "root") {
+        findings.push(
+            Finding::new(
+                "config",
+                Level::Warning,
+                format!("unknown field '{}' in '{}'", key, display),
+            )
+            .suggest("remove the field, it is ignored"),
+        );
+    }
+
+    let roots = match object.get("root").map(|r| r.as_array()) {
+        Some(Some(roots)) => roots,
+        _ => {
+            findings.push(
+                Finding::new(
+                    "config",
+                    Level::Error,
+                    format!("the field 'root' of '{}' is not an array", display),
+                )
+                .suggest(reset),
+            );
+
+            return findings;
+        }
+    };
+
+    let mut seen: Vec<&str> = vec![];
+
+    for root in roots {
+        match root.as_str() {
+            None => findings.push(
+                Finding::new(
+                    "config",
+                    Level::Error,
+                    format!("the root {} is not a string", root),
+                )
+                .suggest("run 'gpm config remove root' and add the roots again"),
+            ),
+            Some(r) if !Path::new(r).is_absolute() => findings.push(
+                Finding::new(
+                    "config",
+                    Level::Warning,
+                    format!("the root '{}' is not an absolute path", r),
+                )
+                .suggest("add the root again with 'gpm config add root <folder>'"),
+            ),
+            Some(r) if seen.contains(&r) => findings.push(
+                Finding::new(
+                    "config",
+                    Level::Warning,
+                    format!("the root '{}' is duplicated", r),
+                )
+                .suggest("run 'gpm config set root <folder>'"),
+            ),
+            Some(r) => seen.push(r),
+        }
+    }
+
+    if roots.is_empty() {
+        findings.push(
+            Finding::new(
+                "config",
+                Level::Warning,
+                "no root is configured".to_string(),
+            )
+            .suggest("run 'gpm config add root $HOME/gpm'"),
+        );
+    }
+
+    if findings.is_empty() {
+        findings.push(Finding::new(
+            "config",
+            Level::Ok,
+            format!("'{}' is valid", display),
+        ));
+    }
+
+    findings
+}
+
+// Whether a file can be created in the folder
+fn is_writable(dir: &Path) -> bool {
+    let probe = dir.join(format!(".gpm-doctor-{}", process::id()));
+
+    let writable = OpenOptions::new()
+        .write(true)
+        .create_new(true)
+        .open(&probe)
+        .is_ok();
+
+    fs::remove_file(&probe).ok();
+
+    writable
+}
+
+pub fn check_roots(roots: &[String]) -> Vec<Finding> {
+    roots
+        .iter()
+        .map(|root| {
+            let path = Path::new(root);
+
+            if !path.exists() {
+                Finding::new(
+                    "root",
+                    Level::Error,
+                    format!("the root '{}' does not exist", root),
+                )
+                .suggest("create the folder, or remove it with 'gpm config remove root'")
+                .fix(Fix::CreateRoot(path.to_path_buf()))
+            } else if !path.is_dir() {
+                Finding::new(
+                    "root",
+                    Level::Error,
+                    format!("the root '{}' is not a folder", root),
+                )
+                .suggest("set another root with 'gpm config set root <folder>'")
+            } else if !is_writable(path) {
+                Finding::new(
+                    "root",
+                    Level::Error,
+                    format!("the root '{}' is not writable", root),
+                )
+                .suggest("change the permission of the folder")
+            } else {
+                Finding::new("root", Level::Ok, format!("'{}' is writable", root))
+            }
+        })
+        .collect()
+}
+
+pub fn check_openers() -> Vec<Finding> {
+    let explorer = match file_explorer::find() {
+        Some(path) => Finding::new(
+            "opener",
+            Level::Ok,
+            format!("file explorer: {}", path.display()),
+        ),
+        None => Finding::new(
+            "opener",
+            Level::Warning,
+            "the command to open the file explorer is not found".to_string(),
+        )
+        .suggest("install it to open the repository with 'gpm open'. eg. xdg-utils on Linux"),
+    };
+
+    let editor = match vscode::find() {
+        Ok(path) => Finding::new(
+            "opener",
+            Level::Ok,
+            format!("Visual Studio Code: {}", path.display()),
+        ),
+        Err(e) => Finding::new("opener", Level::Warning, e.to_string())
+            .suggest("install the 'code' command to open the repository with 'gpm vscode'"),
+    };
+
+    vec![explorer, editor]
+}
+
+// The folders in the layout which are not repositories. eg. $ROOT/host/owner/folder, or the empty host and owner
+fn check_folders(root: &str) -> Vec<Finding> {
+    let mut findings: Vec<Finding> = vec![];
+
+    let read_dirs = |dir: &Path| -> Vec<PathBuf> {
+        let mut dirs = fs::read_dir(dir)
+            .map(|entries| {
+                entries
+                    .flatten()
+                    .map(|e| e.path())
+                    .filter(|p| p.is_dir())
+                    .collect::<Vec<PathBuf>>()
+            })
+            .unwrap_or_default();
+
+        dirs.sort();
+        dirs
+    };
+
+    let is_empty = |dir: &Path| fs::read_dir(dir).is_ok_and(|mut e| e.next().is_none());
+
+    let empty = |path: PathBuf| {
+        Finding::new(
+            "layout",
+            Level::Warning,
+            format!("the folder '{}' is empty", path.display()),
+        )
+        .suggest("remove the folder")
+        .fix(Fix::RemoveEmptyFolder {
+            root: root.to_string(),
+            path,
+        })
+    };
+
+    // the hidden folders are used by gpm. eg. the trash
+    let hosts = read_dirs(Path::new(root)).into_iter().filter(|p| {
+        !p.file_name()
+            .is_some_and(|n| n.to_string_lossy().starts_with('.'))
+    });
+
+    for host in hosts {
+        if is_empty(&host) {
+            findings.push(empty(host));
+            continue;
+        }
+
+        for owner in read_dirs(&host) {
+            if is_empty(&owner) {
+                findings.push(empty(owner));
+                continue;
+            }
+
+            for dir in read_dirs(&owner) {
+                if git::is_repository(&dir) {
+                    continue;
+                }
+
+                if is_empty(&dir) {
+                    findings.push(empty(dir));
+                    continue;
+                }
+
+                findings.push(
+                    Finding::new(
+                        "layout",
+                        Level::Warning,
+                        format!("the folder '{}' is not a git repository", dir.display()),
+                    )
+                    .suggest("move it out of the root, or remove it"),
+                );
+            }
+        }
+    }
+
+    findings
+}
+
+// Check the repository itself, return None if it is fine
+fn check_repository(repo: &Repository) -> Option<Finding> {
+    let path = repo.path_str();
+
+    if git::output(&repo.path, &["rev-parse", "--git-dir"]).is_err() {
+        return Some(
+            Finding::new(
+                "layout",
+                Level::Error,
+                format!("the repository '{}' is broken", path),
+            )
+            .suggest("clone it again, the broken one can be removed by 'gpm rm --force'"),
+        );
+    }
+
+    match git::remote_url(&repo.path, "origin") {
+        Some(_) => None,
+        None => Some(
+            Finding::new(
+                "layout",
+                Level::Warning,
+                format!("the repository '{}' has no origin", path),
+            )
+            .suggest(
+                "add the origin with 'git remote add origin <url>', or move it out of the root",
+            ),
+        ),
+    }
+}
+
+// Check the folders, the repositories and the leftovers of roots
+pub fn check_layout(roots: &[String], repositories: &[Repository]) -> Vec<Finding> {
+    let mut findings: Vec<Finding> = vec![];
+
+    for root in roots.iter().filter(|r| Path::new(r).is_dir()) {
+        match staging::leftovers(root) {
+            Ok(leftovers) if !leftovers.is_empty() => findings.push(
+                Finding::new(
+                    "layout",
+                    Level::Warning,
+                    format!(
+                        "{} partial clones are left in '{}'",
+                        leftovers.len(),
+                        Path::new(root).join(staging::STAGING_DIR).display()
+                    ),
+                )
+                .suggest("remove them, or they are removed by the next 'gpm clone'")
+                .fix(Fix::CleanStaging(root.to_string())),
+            ),
+            Ok(_) => {}
+            Err(e) => findings.push(Finding::new(
+                "layout",
+                Level::Warning,
+                format!("can not read the staging folder of '{}': {}", root, e),
+            )),
+        }
+
+        findings.extend(check_folders(root));
+    }
+
+    let repositories = repositories
+        .iter()
+        .filter(|r| git::is_repository(&r.path))
+        .collect::<Vec<&Repository>>();
+
+    let mut healthy: Vec<Repository> = vec![];
+
+    for repo in repositories {
+        match check_repository(repo) {
+            Some(finding) => findings.push(finding),
+            None => healthy.push(repo.clone()),
+        }
+    }
+
+    for repo in &healthy {
+        match relocate::check(repo) {
+            Ok(Some(mismatch)) => findings.push(
+                Finding::new(
+                    "layout",
+                    Level::Warning,
+                    format!(
+                        "the repository '{}' does not match the origin '{}'",
+                        repo.path_str(),
+                        mismatch.origin
+                    ),
+                )
+                .suggest(&format!(
+                    "move it to '{}' with 'gpm relocate'",
+                    mismatch.dest.display()
+                )),
+            ),
+            Ok(None) => {}
+            Err(e) => findings.push(Finding::new(
+                "layout",
+                Level::Warning,
+                format!("can not check '{}': {}", repo.path_str(), e),
+            )),
+        }
+    }
+
+    for group in dedupe::find(healthy) {
+        findings.push(
+            Finding::new(
+                "layout",
+                Level::Warning,
+                format!(
+                    "the remote '{}' is cloned {} times: {}",
+                    group.remote,
+                    group.members.len(),
+                    group
+                        .members
+                        .iter()
+                        .map(|m| m.repo.path_str())
+                        .collect::<Vec<&str>>()
+                        .join(", ")
+                ),
+            )
+            .suggest("merge and remove the extra clones with 'gpm dedupe'"),
+        );
+    }
+
+    if !findings.iter().any(|f| f.check == "layout") {
+        findings.push(Finding::new(
+            "layout",
+            Level::Ok,
+            "the repositories are in place".to_string(),
+        ));
+    }
+
+    findings
+}
+
+// The structured output of findings
+pub fn output(findings: &[Finding], results: &[Option<String>]) -> Output {
+    let mut output = Output::new(
+        "doctor",
+        &["check", "level", "message", "suggestion", "fixable", "fix"],
+    );
+
+    for (finding, result) in findings.iter().zip(results) {
+        output.push(vec![
+            json!(finding.check),
+            json!(finding.level.name()),
+            json!(finding.message),
+            json!(finding.suggestion),
+            json!(finding.fix.is_some()),
+            json!(result),
+        ]);
+    }
+
+    output
+}
+
+// The report of findings, the suggestion and the result of fix are printed under the finding
+pub fn report(findings: &[Finding], results: &[Option<String>]) -> Vec<String> {
+    let mut lines: Vec<String> = vec![];
+
+    for (finding, result) in findings.iter().zip(results) {
+        lines.push(format!(
+            "{:8} {:7} {}",
+            finding.level.name(),
+            finding.check,
+            finding.message
+        ));
+
+        match (result, &finding.suggestion) {
+            (Some(result), _) => lines.push(format!("{:17}{}", "", result)),
+            (None, Some(suggestion)) => lines.push(format!("{:17}fix: {}", "", suggestion)),
+            (None, None) => {}
+        }
+    }
+
+    let count = |level: Level| findings.iter().filter(|f| f.level == level).count();
+    let fixable = findings
+        .iter()
+        .zip(results)
+        .filter(|(f, r)| f.fix.is_some() && r.is_none())
+        .count();
+
+    let mut summary = format!(
+        "{} errors, {} warnings",
+        count(Level::Error),
+        count(Level::Warning)
+    );
+
+    if fixable > 0 {
+        summary.push_str(&format!(", {} can be fixed with --fix", fixable));
+    }
+
+    lines.push(summary);
+
+    lines
+}
+
+#[cfg(test)]
+mod tests {
+    use std::fs;
+
+    use crate::doctor::{self, Fix, Level};
+    use crate::repository::Repository;
+    use crate::testing;
+
+    #[test]
+    fn test_parse_git_version() {
+        assert_eq!(
+            doctor::parse_git_version("git version 2.39.3 (Apple Git-145)"),
+            Some((2, 39))
+        );
+        assert_eq!(
+            doctor::parse_git_version("git version 2.43.0.windows.1"),
+            Some((2, 43))
+        );
+        assert_eq!(doctor::parse_git_version("git"), None);
+        assert_eq!(doctor::check_git().level, Level::Ok);
+    }
+
+    #[test]
+    fn test_check_config() {
+        let dir = testing::temp_dir("doctor-config");
+        let rc = dir.join(".gpmrc");
+
+        fs::write(&rc, r#"{"root": ["/gpm"]}"#).unwrap();
+        assert_eq!(doctor::check_config(&rc)[0].level, Level::Ok);
+
+        fs::write(
+            &rc,
+            r#"{"root": ["/gpm", "gpm", "/gpm", 1], "editor": "vim"}"#,
+        )
+        .unwrap();
+
+        let findings = doctor::check_config(&rc);
+
+        assert_eq!(
+            findings.iter().map(|f| f.level).collect::<Vec<Level>>(),
+            vec![Level::Warning, Level::Warning, Level::Warning, Level::Error]
+        );
+
+        fs::write(&rc, r#"{"root": "#).unwrap();
+        assert_eq!(doctor::check_config(&rc)[0].level, Level::Error);
+
+        fs::remove_dir_all(dir).unwrap();
+    }
+
+    #[test]
+    fn test_check_roots_and_layout() {
+        let root = testing::temp_dir("doctor-root");
+        let root_str = root.to_str().unwrap().to_string();
+        let missing = root.join("missing").to_str().unwrap().to_string();
+
+        let findings = doctor::check_roots(&[root_str.clone(), missing.clone()]);
+
+        assert_eq!(findings[0].level, Level::Ok);
+        assert_eq!(findings[1].level, Level::Error);
+
+        findings[1].fix.as_ref().unwrap().apply().unwrap();
+        fs::remove_dir(&missing).unwrap();
+
+        let owner = root.join("github.com").join("axetroy");
+        let repo = owner.join("gpm");
+        let stray = owner.join("notes");
+        let empty = root.join("gitlab.com").join("axetroy");
+
+        fs::create_dir_all(&repo).unwrap();
+        fs::create_dir_all(&stray).unwrap();
+        fs::create_dir_all(&empty).unwrap();
+        fs::write(stray.join("todo.md"), "").unwrap();
+
+        testing::git(&repo, &["init", "-q", "-b", "main"]);
+        testing::git(
+            &repo,
+            &[
+                "remote",
+                "add",
+                "origin",
+                "https://github.com/axetroy/gpm.rs",
+            ],
+        );
+
+        let repositories = [&repo, &stray]
+            .iter()
+            .map(|p| Repository::new(&root_str, p.to_path_buf()))
+            .collect::<Vec<Repository>>();
+
+        let findings = doctor::check_layout(std::slice::from_ref(&root_str), &repositories);
+
+        let messages = findings
+            .iter()
+            .map(|f| f.message.as_str())
+            .collect::<Vec<&str>>();
+
+        assert_eq!(findings.len(), 3, "{:?}", messages);
+        // the stray folder is left to the user
+        assert!(findings[0].fix.is_none());
+        assert!(matches!(
+            findings[1].fix,
+            Some(Fix::RemoveEmptyFolder { .. })
+        ));
+        // the relocation needs the confirmation of user
+        assert!(findings[2].fix.is_none());
+
+        for finding in &findings {
+            if let Some(fix) = &finding.fix {
+                fix.apply().unwrap();
+            }
+        }
+
+        assert!(!root.join("gitlab.com").exists());
+        assert!(repo.join(".git").exists());
+
+        fs::remove_dir_all(root).unwrap();
+    }
+}
@@ -0,0 +1,250 @@
+#![deny(warnings)]
+
+use crate::format::Output;
+use crate::parallel;
+use crate::repository::Repository;
+use eyre::Report;
+use serde_json::json;
+use std::io::{self, BufRead, BufReader, Write};
+use std::process::{Command as ChildProcess, Stdio};
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::thread;
+use std::time::{Duration, Instant};
+
+// How to print the output of command
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum OutputMode {
+    // print each line with the repository prefix as soon as it comes
+    Prefixed,
+    // print the whole output of repository when the command finished
+    Grouped,
+}
+
+#[derive(Debug, Clone)]
+pub struct Options {
+    pub command: Vec<String>,
+    pub jobs: usize,
+    pub mode: OutputMode,
+    pub fail_fast: bool,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub enum ExecResult {
+    Exited(Option<i32>, Duration),
+    Failed(String),
+    Skipped,
+}
+
+impl OutputMode {
+    pub const NAMES: [&'static str; 2] = ["prefixed", "grouped"];
+
+    pub fn parse(name: &str) -> Result<OutputMode, Report> {
+        match name {
+            "prefixed" => Ok(OutputMode::Prefixed),
+            "grouped" => Ok(OutputMode::Grouped),
+            _ => Err(Report::msg(format!("unknown output mode '{}'", name))),
+        }
+    }
+}
+
+impl ExecResult {
+    pub fn is_success(&self) -> bool {
+        matches!(self, ExecResult::Exited(Some(0), _))
+    }
+}
+
+// The environment variables for the command run in repository
+pub fn env(repo: &Repository) -> Vec<(&'static str, String)> {
+    vec![
+        ("GPM_ROOT", repo.root.clone()),
+        ("GPM_REPO_HOST", repo.host.clone()),
+        ("GPM_REPO_OWNER", repo.owner.clone()),
+        ("GPM_REPO_NAME", repo.name.clone()),
+        ("GPM_REPO_PATH", repo.path_str().to_string()),
+    ]
+}
+
+fn prefix(repo: &Repository) -> String {
+    format!("[{}/{}/{}]", repo.host, repo.owner, repo.name)
+}
+
+fn run_one(repo: &Repository, options: &Options) -> Result<Option<i32>, Report> {
+    let (program, args) = options
+        .command
+        .split_first()
+        .ok_or_else(|| Report::msg("command is required"))?;
+
+    let mut child = ChildProcess::new(program)
+        .args(args)
+        .current_dir(&repo.path)
+        .envs(env(repo))
+        .stdin(Stdio::null())
+        .stdout(Stdio::piped())
+        .stderr(Stdio::piped())
+        .spawn()?;
+
+    match options.mode {
+        OutputMode::Prefixed => {
+            let stdout = child.stdout.take().unwrap();
+            let stderr = child.stderr.take().unwrap();
+            let prefix = prefix(repo);
+
+            thread::scope(|scope| {
+                scope.spawn(|| {
+                    for line in BufReader::new(stdout).lines().map_while(Result::ok) {
+                        println!("{} {}", prefix, line);
+                    }
+                });
+                scope.spawn(|| {
+                    for line in BufReader::new(stderr).lines().map_while(Result::ok) {
+                        eprintln!("{} {}", prefix, line);
+                    }
+                });
+            });
+
+            Ok(child.wait()?.code())
+        }
+        OutputMode::Grouped => {
+            let output = child.wait_with_output()?;
+
+            let stdout = io::stdout();
+            let mut lock = stdout.lock();
+
+            writeln!(lock, "{} {}", prefix(repo), repo.path_str())?;
+            lock.write_all(&output.stdout)?;
+            lock.flush()?;
+            drop(lock);
+
+            io::stderr().write_all(&output.stderr)?;
+
+            Ok(output.status.code())
+        }
+    }
+}
+
+// Run the command in each repository, the results keep the order of repositories
+pub fn run(repositories: &[Repository], options: &Options) -> Vec<ExecResult> {
+    let failed = AtomicBool::new(false);
+
+    parallel::map(repositories, options.jobs, |repo| {
+        if options.fail_fast && failed.load(Ordering::SeqCst) {
+            return ExecResult::Skipped;
+        }
+
+        let start = Instant::now();
+
+        let result = match run_one(repo, options) {
+            Ok(code) => ExecResult::Exited(code, start.elapsed()),
+            Err(e) => ExecResult::Failed(e.to_string()),
+        };
+
+        if !result.is_success() {
+            failed.store(true, Ordering::SeqCst);
+        }
+
+        result
+    })
+}
+
+// The summary of results for printing
+pub fn output(repositories: &[Repository], results: &[ExecResult]) -> Output {
+    let fields = Repository::FIELDS
+        .into_iter()
+        .chain(["result", "code", "duration"])
+        .collect::<Vec<&str>>();
+
+    let mut output = Output::new("exec", &fields).with_tree_levels(3);
+
+    for (repo, result) in repositories.iter().zip(results.iter()) {
+        let mut record = repo.fields();
+
+        record.extend(match result {
+            ExecResult::Exited(code, duration) => [
+                json!(if result.is_success() { "ok" } else { "failed" }),
+                json!(code),
+                json!(format!("{:.2}s", duration.as_secs_f64())),
+            ],
+            ExecResult::Failed(e) => [json!(format!("error: {}", e)), json!(null), json!(null)],
+            ExecResult::Skipped => [json!("skipped"), json!(null), json!(null)],
+        });
+
+        output.push(record);
+    }
+
+    output
+}
+
+#[cfg(test)]
+mod tests {
+    use std::fs;
+
+    use crate::exec::{self, ExecResult, Options, OutputMode};
+    use crate::repository::Repository;
+    use crate::testing;
+
+    #[cfg(target_family = "unix")]
+    #[test]
+    fn test_exec_run() {
+        let root = testing::temp_dir("exec-root");
+
+        let repositories = ["a", "b", "c"]
+            .iter()
+            .map(|name| {
+                let path = root.join("github.com").join("axetroy").join(name);
+
+                fs::create_dir_all(&path).unwrap();
+
+                Repository::new(root.to_str().unwrap(), path)
+            })
+            .collect::<Vec<Repository>>();
+
+        let command = vec![
+            "sh".to_string(),
+            "-c".to_string(),
+            "test \"$GPM_REPO_OWNER/$GPM_REPO_NAME\" != axetroy/b && test \"$(basename \"$PWD\")\" = \"$GPM_REPO_NAME\""
+                .to_string(),
+        ];
+
+        let r1 = exec::run(
+            &repositories,
+            &Options {
+                command: command.clone(),
+                jobs: 2,
+                mode: OutputMode::Grouped,
+                fail_fast: false,
+            },
+        );
+
+        assert!(r1[0].is_success());
+        assert!(matches!(r1[1], ExecResult::Exited(Some(1), _)));
+        assert!(r1[2].is_success());
+
+        let r2 = exec::run(
+            &repositories,
+            &Options {
+                command,
+                jobs: 1,
+                mode: OutputMode::Prefixed,
+                fail_fast: true,
+            },
+        );
+
+        assert!(r2[0].is_success());
+        assert!(!r2[1].is_success());
+        assert_eq!(r2[2], ExecResult::Skipped);
+
+        let r3 = exec::run(
+            &repositories[..1],
+            &Options {
+                command: vec!["command-not-exist-for-gpm".to_string()],
+                jobs: 1,
+                mode: OutputMode::Prefixed,
+                fail_fast: false,
+            },
+        );
+
+        assert!(matches!(r3[0], ExecResult::Failed(_)));
+
+        fs::remove_dir_all(root).unwrap();
+    }
+}
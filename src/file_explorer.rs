@@ -1,7 +1,8 @@
 #![deny(warnings)]
 
-use std::path::Path;
+use std::path::{Path, PathBuf};
 use std::process::Command as ChildProcess;
+use which::which;
 
 // https://stackoverflow.com/questions/66485945/with-rust-open-explorer-on-a-file
 #[cfg(target_os = "macos")]
@@ -13,7 +14,13 @@ static OPEN_COMMAND: &str = "xdg-open";
 #[cfg(target_os = "windows")]
 static OPEN_COMMAND: &str = "explorer";
 
+// Find the command to open the file explorer
+pub fn find() -> Option<PathBuf> {
+    which(OPEN_COMMAND).ok()
+}
+
 // Open a path in file explorer
+#[allow(clippy::zombie_processes)]
 pub fn open(folder: &Path) {
     ChildProcess::new(OPEN_COMMAND)
         .arg(folder.as_os_str().to_str().unwrap())
@@ -0,0 +1,515 @@
+#![deny(warnings)]
+
+use crate::{git, parallel, repository::Repository, util};
+use clap::{Arg, ArgMatches};
+use eyre::Report;
+use path_absolutize::*;
+use regex::Regex;
+use std::path::Path;
+use std::time::{Duration, SystemTime, UNIX_EPOCH};
+
+// The pattern of text, regex if surrounded by '/', otherwise glob with '*' and '?'
+#[derive(Debug, Clone)]
+pub struct Pattern(Regex);
+
+#[derive(Debug, Clone)]
+pub enum Term {
+    Host(Pattern),
+    Owner(Pattern),
+    Name(Pattern),
+    Root(String),
+    Dirty,
+    Ahead,
+    Behind,
+    NoRemote,
+    Stale(Duration),
+}
+
+#[derive(Debug, Clone)]
+pub enum Expr {
+    Term(Term),
+    Not(Box<Expr>),
+    And(Box<Expr>, Box<Expr>),
+    Or(Box<Expr>, Box<Expr>),
+}
+
+// The filter of repositories, all the conditions must be matched
+#[derive(Debug, Clone, Default)]
+pub struct Filter {
+    conditions: Vec<Expr>,
+}
+
+impl Pattern {
+    pub fn parse(pattern: &str) -> Result<Pattern, Report> {
+        let regex = if pattern.len() >= 2 && pattern.starts_with('/') && pattern.ends_with('/') {
+            Regex::new(&pattern[1..pattern.len() - 1])?
+        } else {
+            let mut expr = String::from("^");
+
+            for c in pattern.chars() {
+                match c {
+                    '*' => expr.push_str(".*"),
+                    '?' => expr.push('.'),
+                    _ => expr.push_str(&regex::escape(&c.to_string())),
+                }
+            }
+
+            expr.push('$');
+
+            Regex::new(&expr)?
+        };
+
+        Ok(Pattern(regex))
+    }
+
+    pub fn is_match(&self, text: &str) -> bool {
+        self.0.is_match(text)
+    }
+}
+
+impl Term {
+    pub fn parse(term: &str) -> Result<Term, Report> {
+        let (key, value) = match term.split_once(':') {
+            Some((key, value)) => (key, Some(value)),
+            None => (term, None),
+        };
+
+        let term = match (key, value) {
+            ("host", Some(v)) => Term::Host(Pattern::parse(v)?),
+            ("owner", Some(v)) => Term::Owner(Pattern::parse(v)?),
+            ("name", Some(v)) => Term::Name(Pattern::parse(v)?),
+            ("root", Some(v)) => Term::Root(normalize_root(v)),
+            ("stale", Some(v)) => Term::Stale(util::parse_duration(v)?),
+            ("dirty", None) => Term::Dirty,
+            ("ahead", None) => Term::Ahead,
+            ("behind", None) => Term::Behind,
+            ("no-remote", None) => Term::NoRemote,
+            _ => return Err(Report::msg(format!("unknown filter '{}'", term))),
+        };
+
+        Ok(term)
+    }
+
+    pub fn is_match(&self, repo: &Repository) -> bool {
+        let dir = repo.path.as_path();
+
+        match self {
+            Term::Host(p) => p.is_match(&repo.host),
+            Term::Owner(p) => p.is_match(&repo.owner),
+            Term::Name(p) => p.is_match(&repo.name),
+            Term::Root(root) => &normalize_root(&repo.root) == root,
+            Term::Dirty => git::is_repository(dir) && git::is_dirty(dir).unwrap_or(false),
+            Term::Ahead => {
+                git::is_repository(dir)
+                    && matches!(git::ahead_behind(dir), Ok(Some((ahead, _))) if ahead > 0)
+            }
+            Term::Behind => {
+                git::is_repository(dir)
+                    && matches!(git::ahead_behind(dir), Ok(Some((_, behind))) if behind > 0)
+            }
+            Term::NoRemote => {
+                git::is_repository(dir) && git::output(dir, &["remote"]).is_ok_and(|r| r.is_empty())
+            }
+            Term::Stale(duration) => {
+                if !git::is_repository(dir) {
+                    return false;
+                }
+
+                let now = SystemTime::now()
+                    .duration_since(UNIX_EPOCH)
+                    .unwrap()
+                    .as_secs();
+
+                match git::output(dir, &["log", "-1", "--format=%ct"]) {
+                    Ok(time) => match time.parse::<u64>() {
+                        Ok(time) => now.saturating_sub(time) > duration.as_secs(),
+                        Err(_) => false,
+                    },
+                    // no commit yet
+                    Err(_) => false,
+                }
+            }
+        }
+    }
+}
+
+impl Expr {
+    // Parse the query expression. eg. host:github.com and (dirty or ahead) and not owner:axetroy
+    pub fn parse(query: &str) -> Result<Expr, Report> {
+        let tokens = tokenize(query)?;
+        let mut parser = Parser { tokens, index: 0 };
+
+        let expr = parser.parse_or()?;
+
+        match parser.peek() {
+            None => Ok(expr),
+            Some(token) => Err(Report::msg(format!(
+                "unexpected '{}' in query '{}'",
+                token, query
+            ))),
+        }
+    }
+
+    pub fn is_match(&self, repo: &Repository) -> bool {
+        match self {
+            Expr::Term(term) => term.is_match(repo),
+            Expr::Not(expr) => !expr.is_match(repo),
+            Expr::And(left, right) => left.is_match(repo) && right.is_match(repo),
+            Expr::Or(left, right) => left.is_match(repo) || right.is_match(repo),
+        }
+    }
+}
+
+impl Filter {
+    pub fn new(conditions: Vec<Expr>) -> Filter {
+        Filter { conditions }
+    }
+
+    // Create filter from the arguments that defined in `args()`
+    pub fn from_matches(matches: &ArgMatches) -> Result<Filter, Report> {
+        let mut conditions: Vec<Expr> = vec![];
+
+        for (arg, key) in [
+            ("host", "host"),
+            ("owner", "owner"),
+            ("name", "name"),
+            ("root", "root"),
+            ("stale", "stale"),
+        ] {
+            for value in matches.values_of(arg).unwrap_or_default() {
+                conditions.push(Expr::Term(Term::parse(&format!("{}:{}", key, value))?));
+            }
+        }
+
+        for flag in ["dirty", "ahead", "behind", "no-remote"] {
+            if matches.is_present(flag) {
+                conditions.push(Expr::Term(Term::parse(flag)?));
+            }
+        }
+
+        if let Some(query) = matches.value_of("query") {
+            conditions.push(Expr::parse(query)?);
+        }
+
+        Ok(Filter::new(conditions))
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.conditions.is_empty()
+    }
+
+    pub fn is_match(&self, repo: &Repository) -> bool {
+        self.conditions.iter().all(|c| c.is_match(repo))
+    }
+
+    // Keep the repositories which matched, the order is kept
+    pub fn apply(&self, repositories: Vec<Repository>) -> Vec<Repository> {
+        if self.is_empty() {
+            return repositories;
+        }
+
+        let matched = parallel::map(&repositories, parallel::default_jobs(), |r| {
+            self.is_match(r)
+        });
+
+        repositories
+            .into_iter()
+            .zip(matched)
+            .filter(|(_, matched)| *matched)
+            .map(|(r, _)| r)
+            .collect()
+    }
+}
+
+// The arguments of filter for the commands which operate on multiple repositories
+pub fn args<'help>() -> Vec<Arg<'help>> {
+    vec![
+        Arg::new("host")
+            .long("host")
+            .help("Filter by host, glob or /regex/")
+            .takes_value(true)
+            .multiple_occurrences(true),
+        Arg::new("owner")
+            .long("owner")
+            .help("Filter by owner, glob or /regex/")
+            .takes_value(true)
+            .multiple_occurrences(true),
+        Arg::new("name")
+            .long("name")
+            .help("Filter by repository name, glob or /regex/")
+            .takes_value(true)
+            .multiple_occurrences(true),
+        Arg::new("root")
+            .long("root")
+            .help("Filter by the root folder")
+            .takes_value(true)
+            .multiple_occurrences(true),
+        Arg::new("stale")
+            .long("stale")
+            .help("Filter the repositories without commit in the duration. eg. 90d")
+            .takes_value(true),
+        Arg::new("dirty")
+            .long("dirty")
+            .help("Filter the repositories with uncommitted changes")
+            .takes_value(false),
+        Arg::new("ahead")
+            .long("ahead")
+            .help("Filter the repositories ahead of upstream")
+            .takes_value(false),
+        Arg::new("behind")
+            .long("behind")
+            .help("Filter the repositories behind upstream")
+            .takes_value(false),
+        Arg::new("no-remote")
+            .long("no-remote")
+            .help("Filter the repositories without remote")
+            .takes_value(false),
+        Arg::new("query")
+            .short('q')
+            .long("query")
+            .help("Filter by query expression. eg. 'host:github.com and (dirty or ahead)'")
+            .takes_value(true),
+    ]
+}
+
+fn normalize_root(root: &str) -> String {
+    Path::new(root)
+        .absolutize()
+        .map(|p| {
+            p.to_string_lossy()
+                .trim_end_matches(['/', '\\'])
+                .to_string()
+        })
+        .unwrap_or_else(|_| root.to_string())
+}
+
+fn tokenize(query: &str) -> Result<Vec<String>, Report> {
+    let mut tokens: Vec<String> = vec![];
+    let mut chars = query.chars().peekable();
+
+    while let Some(&c) = chars.peek() {
+        match c {
+            c if c.is_whitespace() => {
+                chars.next();
+            }
+            '(' | ')' | '!' => {
+                tokens.push(c.to_string());
+                chars.next();
+            }
+            '&' | '|' => {
+                chars.next();
+
+                if chars.next() != Some(c) {
+                    return Err(Report::msg(format!("expect '{}{}' in query", c, c)));
+                }
+
+                tokens.push(if c == '&' { "and" } else { "or" }.to_string());
+            }
+            _ => {
+                let mut token = String::new();
+
+                while let Some(&c) = chars.peek() {
+                    if c.is_whitespace() || c == '(' || c == ')' {
+                        break;
+                    }
+
+                    chars.next();
+
+                    // quoted value. eg. name:"my project"
+                    if c == '"' {
+                        for c in chars.by_ref() {
+                            if c == '"' {
+                                break;
+                            }
+                            token.push(c);
+                        }
+                    } else {
+                        token.push(c);
+                    }
+                }
+
+                tokens.push(token);
+            }
+        }
+    }
+
+    Ok(tokens)
+}
+
+struct Parser {
+    tokens: Vec<String>,
+    index: usize,
+}
+
+impl Parser {
+    fn peek(&self) -> Option<&str> {
+        self.tokens.get(self.index).map(|s| s.as_str())
+    }
+
+    fn next(&mut self) -> Option<String> {
+        let token = self.tokens.get(self.index).cloned();
+        self.index += 1;
+        token
+    }
+
+    fn parse_or(&mut self) -> Result<Expr, Report> {
+        let mut expr = self.parse_and()?;
+
+        while self.peek() == Some("or") {
+            self.next();
+            expr = Expr::Or(Box::new(expr), Box::new(self.parse_and()?));
+        }
+
+        Ok(expr)
+    }
+
+    fn parse_and(&mut self) -> Result<Expr, Report> {
+        let mut expr = self.parse_not()?;
+
+        loop {
+            match self.peek() {
+                Some("and") => {
+                    self.next();
+                }
+                // terms next to each other mean 'and'
+                Some(token) if token != "or" && token != ")" => {}
+                _ => break,
+            }
+
+            expr = Expr::And(Box::new(expr), Box::new(self.parse_not()?));
+        }
+
+        Ok(expr)
+    }
+
+    fn parse_not(&mut self) -> Result<Expr, Report> {
+        match self.peek() {
+            Some("not") | Some("!") => {
+                self.next();
+                Ok(Expr::Not(Box::new(self.parse_not()?)))
+            }
+            _ => self.parse_primary(),
+        }
+    }
+
+    fn parse_primary(&mut self) -> Result<Expr, Report> {
+        match self.next() {
+            Some(token) if token == "(" => {
+                let expr = self.parse_or()?;
+
+                match self.next() {
+                    Some(token) if token == ")" => Ok(expr),
+                    _ => Err(Report::msg("missing ')' in query")),
+                }
+            }
+            Some(token) if token == ")" || token == "and" || token == "or" => {
+                Err(Report::msg(format!("unexpected '{}' in query", token)))
+            }
+            Some(token) => Ok(Expr::Term(Term::parse(&token)?)),
+            None => Err(Report::msg("unexpected end of query")),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::{fs, path::PathBuf};
+
+    use crate::filter::{Expr, Filter, Pattern};
+    use crate::repository::Repository;
+    use crate::testing;
+
+    fn repo(host: &str, owner: &str, name: &str) -> Repository {
+        Repository::new(
+            "/root",
+            PathBuf::from("/root").join(host).join(owner).join(name),
+        )
+    }
+
+    #[test]
+    fn test_pattern() {
+        let p1 = Pattern::parse("gpm*").unwrap();
+
+        assert!(p1.is_match("gpm.rs"));
+        assert!(p1.is_match("gpm"));
+        assert!(!p1.is_match("my-gpm"));
+
+        let p2 = Pattern::parse("g?m.rs").unwrap();
+
+        assert!(p2.is_match("gpm.rs"));
+        assert!(!p2.is_match("gpmxrs"));
+
+        let p3 = Pattern::parse("/^gpm\\.(rs|js)$/").unwrap();
+
+        assert!(p3.is_match("gpm.rs"));
+        assert!(p3.is_match("gpm.js"));
+        assert!(!p3.is_match("gpm.go"));
+
+        assert!(Pattern::parse("/(/").is_err());
+    }
+
+    #[test]
+    fn test_query() {
+        let r1 = repo("github.com", "axetroy", "gpm.rs");
+        let r2 = repo("gitlab.com", "axetroy", "gpm.js");
+        let r3 = repo("github.com", "another", "project");
+
+        let q1 = Expr::parse("host:github.com and not owner:another").unwrap();
+
+        assert!(q1.is_match(&r1));
+        assert!(!q1.is_match(&r2));
+        assert!(!q1.is_match(&r3));
+
+        let q2 = Expr::parse("(host:gitlab.com || name:project) && !name:gpm*").unwrap();
+
+        assert!(!q2.is_match(&r1));
+        assert!(!q2.is_match(&r2));
+        assert!(q2.is_match(&r3));
+
+        let q3 = Expr::parse("owner:axetroy name:/\\.rs$/ or name:project").unwrap();
+
+        assert!(q3.is_match(&r1));
+        assert!(!q3.is_match(&r2));
+        assert!(q3.is_match(&r3));
+
+        let q4 = Expr::parse("root:/root").unwrap();
+
+        assert!(q4.is_match(&r1));
+
+        assert!(Expr::parse("host:github.com and").is_err());
+        assert!(Expr::parse("(host:github.com").is_err());
+        assert!(Expr::parse("unknown:value").is_err());
+        assert!(Expr::parse("stale:abc").is_err());
+        assert!(Expr::parse("a & b").is_err());
+    }
+
+    #[test]
+    fn test_filter_by_git() {
+        let remote = testing::init_repo("filter-remote");
+        let repo = testing::clone_repo(&remote, "filter-repo");
+
+        let r1 = Repository::new("/", remote.clone());
+        let r2 = Repository::new("/", repo.clone());
+
+        let f1 = Filter::new(vec![Expr::parse("no-remote").unwrap()]);
+
+        assert_eq!(f1.apply(vec![r1.clone(), r2.clone()]), vec![r1.clone()]);
+
+        testing::commit(&repo, "local");
+        fs::write(remote.join("untracked"), "").unwrap();
+
+        let f2 = Filter::new(vec![Expr::parse("dirty or ahead").unwrap()]);
+
+        assert_eq!(
+            f2.apply(vec![r1.clone(), r2.clone()]),
+            vec![r1.clone(), r2.clone()]
+        );
+
+        let f3 = Filter::new(vec![Expr::parse("stale:1d or behind").unwrap()]);
+
+        assert!(f3.apply(vec![r1, r2]).is_empty());
+
+        fs::remove_dir_all(remote).unwrap();
+        fs::remove_dir_all(repo).unwrap();
+    }
+}
@@ -0,0 +1,407 @@
+#![deny(warnings)]
+
+use clap::{Arg, ArgMatches};
+use eyre::Report;
+use serde_json::{json, Map, Value};
+
+// The version of JSON output, increase it when there is breaking change of the fields
+pub const SCHEMA_VERSION: u32 = 1;
+
+#[derive(Debug, Clone, PartialEq)]
+pub enum Format {
+    Table,
+    Tree,
+    Json,
+    Ndjson,
+    Csv,
+    Paths,
+    Template(String),
+}
+
+// The output of command, the records are rendered with the format
+#[derive(Debug, Clone)]
+pub struct Output {
+    kind: String,
+    columns: Vec<String>,
+    records: Vec<Vec<Value>>,
+    tree_levels: usize,
+}
+
+impl Format {
+    pub const NAMES: [&'static str; 7] = [
+        "table", "tree", "json", "ndjson", "csv", "paths", "template",
+    ];
+
+    pub fn parse(name: &str, template: Option<&str>) -> Result<Format, Report> {
+        let format = match name {
+            "table" => Format::Table,
+            "tree" => Format::Tree,
+            "json" => Format::Json,
+            "ndjson" => Format::Ndjson,
+            "csv" => Format::Csv,
+            "paths" => Format::Paths,
+            "template" => match template {
+                Some(t) => Format::Template(t.to_string()),
+                None => return Err(Report::msg("the template format requires --template")),
+            },
+            _ => return Err(Report::msg(format!("unknown format '{}'", name))),
+        };
+
+        Ok(format)
+    }
+
+    // Create format from the arguments that defined in `args()`, return None if not specified
+    pub fn from_matches(matches: &ArgMatches) -> Result<Option<Format>, Report> {
+        let template = matches.value_of("template");
+
+        match (matches.value_of("format"), template) {
+            (Some(name), _) => Format::parse(name, template).map(Some),
+            (None, Some(t)) => Ok(Some(Format::Template(t.to_string()))),
+            (None, None) => Ok(None),
+        }
+    }
+}
+
+impl Output {
+    pub fn new(kind: &str, columns: &[&str]) -> Output {
+        Output {
+            kind: kind.to_string(),
+            columns: columns.iter().map(|c| c.to_string()).collect(),
+            records: vec![],
+            tree_levels: 1,
+        }
+    }
+
+    // The count of leading columns grouped as the levels of tree format
+    pub fn with_tree_levels(mut self, levels: usize) -> Output {
+        self.tree_levels = levels;
+        self
+    }
+
+    pub fn push(&mut self, record: Vec<Value>) {
+        debug_assert_eq!(record.len(), self.columns.len());
+        self.records.push(record);
+    }
+
+    fn object(&self, record: &[Value]) -> Value {
+        let map = self
+            .columns
+            .iter()
+            .cloned()
+            .zip(record.iter().cloned())
+            .collect::<Map<String, Value>>();
+
+        Value::Object(map)
+    }
+
+    fn value_of<'a>(&self, record: &'a [Value], column: &str) -> Option<&'a Value> {
+        self.columns
+            .iter()
+            .position(|c| c == column)
+            .map(|i| &record[i])
+    }
+
+    pub fn render(&self, format: &Format) -> String {
+        let mut lines: Vec<String> = vec![];
+
+        match format {
+            Format::Table => {
+                let header = self.columns.iter().map(|c| c.to_uppercase()).collect();
+                let rows = self
+                    .records
+                    .iter()
+                    .map(|r| r.iter().map(text).collect::<Vec<String>>());
+
+                let rows = std::iter::once(header).chain(rows).collect::<Vec<_>>();
+
+                let widths = (0..self.columns.len())
+                    .map(|i| rows.iter().map(|r| r[i].chars().count()).max().unwrap_or(0))
+                    .collect::<Vec<usize>>();
+
+                for row in rows {
+                    let line = row
+                        .iter()
+                        .zip(widths.iter())
+                        .map(|(cell, width)| format!("{:width$}", cell, width = width))
+                        .collect::<Vec<String>>()
+                        .join("  ");
+
+                    lines.push(line.trim_end().to_string());
+                }
+            }
+            Format::Tree => {
+                let levels = self.tree_levels.min(self.columns.len());
+                let mut previous: Vec<String> = vec![];
+
+                for record in &self.records {
+                    let groups = record[..levels].iter().map(text).collect::<Vec<String>>();
+
+                    // print the levels which differ from the previous record
+                    let same = groups
+                        .iter()
+                        .zip(previous.iter())
+                        .take_while(|(a, b)| a == b)
+                        .count();
+
+                    for (depth, group) in groups.iter().enumerate().skip(same) {
+                        lines.push(format!("{}{}", "    ".repeat(depth), group));
+                    }
+
+                    let leaf = record[levels..]
+                        .iter()
+                        .map(text)
+                        .collect::<Vec<String>>()
+                        .join("  ");
+
+                    lines.push(format!("{}{}", "    ".repeat(levels), leaf));
+
+                    previous = groups;
+                }
+            }
+            Format::Json => {
+                let items = self
+                    .records
+                    .iter()
+                    .map(|r| self.object(r))
+                    .collect::<Vec<Value>>();
+
+                let output = json!({
+                    "version": SCHEMA_VERSION,
+                    "kind": self.kind,
+                    "items": items,
+                });
+
+                lines.push(output.to_string());
+            }
+            Format::Ndjson => {
+                for record in &self.records {
+                    lines.push(self.object(record).to_string());
+                }
+            }
+            Format::Csv => {
+                lines.push(
+                    self.columns
+                        .iter()
+                        .map(|c| csv_escape(c))
+                        .collect::<Vec<String>>()
+                        .join(","),
+                );
+
+                for record in &self.records {
+                    lines.push(
+                        record
+                            .iter()
+                            .map(|v| match v {
+                                Value::Null => String::new(),
+                                v => csv_escape(&text(v)),
+                            })
+                            .collect::<Vec<String>>()
+                            .join(","),
+                    );
+                }
+            }
+            Format::Paths => {
+                for record in &self.records {
+                    let value = self
+                        .value_of(record, "path")
+                        .or_else(|| record.first())
+                        .map(text)
+                        .unwrap_or_default();
+
+                    lines.push(value);
+                }
+            }
+            Format::Template(template) => {
+                for record in &self.records {
+                    lines.push(self.render_template(template, record));
+                }
+            }
+        }
+
+        lines.join("\n")
+    }
+
+    // Replace the {column} in template with the value, unknown column is kept as it is
+    fn render_template(&self, template: &str, record: &[Value]) -> String {
+        let template = template.replace("\\t", "\t").replace("\\n", "\n");
+        let mut result = String::new();
+        let mut rest = template.as_str();
+
+        while let Some(start) = rest.find('{') {
+            result.push_str(&rest[..start]);
+
+            match rest[start..].find('}') {
+                Some(end) => {
+                    let key = &rest[start + 1..start + end];
+
+                    match self.value_of(record, key) {
+                        Some(v) => result.push_str(&text(v)),
+                        None => result.push_str(&rest[start..=start + end]),
+                    }
+
+                    rest = &rest[start + end + 1..];
+                }
+                None => {
+                    result.push_str(&rest[start..]);
+                    rest = "";
+                }
+            }
+        }
+
+        result.push_str(rest);
+
+        result
+    }
+
+    pub fn print(&self, format: &Format) {
+        let content = self.render(format);
+
+        if !content.is_empty() {
+            println!("{}", content);
+        }
+    }
+}
+
+// The arguments of output format for the commands
+pub fn args<'help>() -> Vec<Arg<'help>> {
+    vec![
+        Arg::new("format")
+            .long("format")
+            .help("The output format")
+            .takes_value(true)
+            .possible_values(Format::NAMES),
+        Arg::new("template")
+            .long("template")
+            .help("The template of each record, the {column} will be replaced. eg. '{host}/{owner}/{name}\\t{path}'")
+            .takes_value(true),
+    ]
+}
+
+// The plain text of value, '-' for null
+fn text(value: &Value) -> String {
+    match value {
+        Value::Null => "-".to_string(),
+        Value::String(s) => s.to_string(),
+        Value::Array(items) => items.iter().map(text).collect::<Vec<String>>().join(","),
+        v => v.to_string(),
+    }
+}
+
+fn csv_escape(field: &str) -> String {
+    if field.contains([',', '"', '\n', '\r']) {
+        format!("\"{}\"", field.replace('"', "\"\""))
+    } else {
+        field.to_string()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::format::{Format, Output};
+    use serde_json::json;
+
+    fn output() -> Output {
+        let mut output = Output::new("repository", &["host", "owner", "name", "path", "size"])
+            .with_tree_levels(2);
+
+        output.push(vec![
+            json!("github.com"),
+            json!("axetroy"),
+            json!("gpm.rs"),
+            json!("/gpm/github.com/axetroy/gpm.rs"),
+            json!(1024),
+        ]);
+        output.push(vec![
+            json!("github.com"),
+            json!("axetroy"),
+            json!("a,\"b\""),
+            json!("/gpm/github.com/axetroy/a"),
+            json!(null),
+        ]);
+
+        output
+    }
+
+    #[test]
+    fn test_format_parse() {
+        assert_eq!(Format::parse("table", None).unwrap(), Format::Table);
+        assert_eq!(
+            Format::parse("template", Some("{name}")).unwrap(),
+            Format::Template("{name}".to_string())
+        );
+        assert!(Format::parse("template", None).is_err());
+        assert!(Format::parse("xml", None).is_err());
+    }
+
+    #[test]
+    fn test_render_table() {
+        assert_eq!(
+            output().render(&Format::Table),
+            [
+                "HOST        OWNER    NAME    PATH                            SIZE",
+                "github.com  axetroy  gpm.rs  /gpm/github.com/axetroy/gpm.rs  1024",
+                "github.com  axetroy  a,\"b\"   /gpm/github.com/axetroy/a       -",
+            ]
+            .join("\n")
+        );
+    }
+
+    #[test]
+    fn test_render_tree() {
+        assert_eq!(
+            output().render(&Format::Tree),
+            [
+                "github.com",
+                "    axetroy",
+                "        gpm.rs  /gpm/github.com/axetroy/gpm.rs  1024",
+                "        a,\"b\"  /gpm/github.com/axetroy/a  -",
+            ]
+            .join("\n")
+        );
+    }
+
+    #[test]
+    fn test_render_json() {
+        assert_eq!(
+            output().render(&Format::Json),
+            r#"{"items":[{"host":"github.com","name":"gpm.rs","owner":"axetroy","path":"/gpm/github.com/axetroy/gpm.rs","size":1024},{"host":"github.com","name":"a,\"b\"","owner":"axetroy","path":"/gpm/github.com/axetroy/a","size":null}],"kind":"repository","version":1}"#
+        );
+
+        assert_eq!(
+            output().render(&Format::Ndjson),
+            [
+                r#"{"host":"github.com","name":"gpm.rs","owner":"axetroy","path":"/gpm/github.com/axetroy/gpm.rs","size":1024}"#,
+                r#"{"host":"github.com","name":"a,\"b\"","owner":"axetroy","path":"/gpm/github.com/axetroy/a","size":null}"#,
+            ]
+            .join("\n")
+        );
+    }
+
+    #[test]
+    fn test_render_csv() {
+        assert_eq!(
+            output().render(&Format::Csv),
+            [
+                "host,owner,name,path,size",
+                "github.com,axetroy,gpm.rs,/gpm/github.com/axetroy/gpm.rs,1024",
+                "github.com,axetroy,\"a,\"\"b\"\"\",/gpm/github.com/axetroy/a,",
+            ]
+            .join("\n")
+        );
+    }
+
+    #[test]
+    fn test_render_paths_and_template() {
+        assert_eq!(
+            output().render(&Format::Paths),
+            "/gpm/github.com/axetroy/gpm.rs\n/gpm/github.com/axetroy/a"
+        );
+
+        assert_eq!(
+            output().render(&Format::Template(
+                "{host}/{owner}/{name}\\t{unknown}{".to_string()
+            )),
+            "github.com/axetroy/gpm.rs\t{unknown}{\ngithub.com/axetroy/a,\"b\"\t{unknown}{"
+        );
+    }
+}
@@ -1,9 +1,13 @@
 #![deny(warnings)]
 
+use crate::network;
+use crate::remote;
 use core::result::Result;
 use eyre::Report;
 use git_url_parse::GitUrl;
 use path_absolutize::*;
+use std::fs;
+use std::io::{self, IsTerminal};
 use std::path::Path;
 use std::path::PathBuf;
 use std::process::Command as ChildProcess;
@@ -14,8 +18,12 @@ pub fn url_to_path(root: &str, url: &str) -> Result<PathBuf, Report> {
         Ok(r) => {
             let mut dir = PathBuf::new();
 
-            let host = r.host.expect("invalid repository host");
-            let owner = r.owner.expect("invalid repository owner");
+            let host = r
+                .host
+                .ok_or_else(|| Report::msg("invalid repository host"))?;
+            let owner = r
+                .owner
+                .ok_or_else(|| Report::msg("invalid repository owner"))?;
 
             if host.is_empty() || owner.is_empty() {
                 return Err(Report::msg("url host or owner is empty"));
@@ -34,31 +42,228 @@ pub fn url_to_path(root: &str, url: &str) -> Result<PathBuf, Report> {
     }
 }
 
-pub fn clone(url: &str, dest: &Path, args: Vec<&str>) -> Result<(), Report> {
-    match ChildProcess::new("git")
-        .arg("clone")
-        .arg(url)
-        .arg(dest.to_str().unwrap())
+// Clone the repository, the partial clone is removed before retry
+pub fn clone(
+    url: &str,
+    dest: &Path,
+    args: Vec<&str>,
+    options: &network::Options,
+) -> Result<(), Report> {
+    let mut clone_args = vec!["clone", url, dest.to_str().unwrap()];
+
+    // the progress is not printed by git if the stderr is not terminal
+    if io::stderr().is_terminal()
+        && !args
+            .iter()
+            .any(|a| ["-q", "--quiet", "--no-progress"].contains(a))
+    {
+        clone_args.push("--progress");
+    }
+
+    clone_args.extend(args);
+
+    network::run(None, &clone_args, options, true, |e, delay| {
+        if dest.exists() {
+            fs::remove_dir_all(dest).ok();
+        }
+
+        eprintln!("{}, retry in {}s", e, delay.as_secs());
+    })?;
+
+    Ok(())
+}
+
+// Whether the folder is the top level of a git repository
+pub fn is_repository(dir: &Path) -> bool {
+    dir.join(".git").exists()
+}
+
+// Get the git folder of repository, it is not `.git` for worktree or submodule
+pub fn git_dir(dir: &Path) -> Result<PathBuf, Report> {
+    Ok(PathBuf::from(output(
+        dir,
+        &["rev-parse", "--absolute-git-dir"],
+    )?))
+}
+
+// Run git command in the repository folder and return the stdout
+pub fn output(dir: &Path, args: &[&str]) -> Result<String, Report> {
+    let output = ChildProcess::new("git")
+        .arg("-C")
+        .arg(dir)
         .args(args)
-        .spawn()
+        .output()?;
+
+    if output.status.success() {
+        Ok(String::from_utf8_lossy(&output.stdout)
+            .trim_end()
+            .to_string())
+    } else {
+        Err(Report::msg(format!(
+            "git {} process fail: {}",
+            args.iter()
+                .map(|a| remote::mask_credentials(a))
+                .collect::<Vec<String>>()
+                .join(" "),
+            String::from_utf8_lossy(&output.stderr).trim()
+        )))
+    }
+}
+
+// Get the current branch name, return None if HEAD is detached
+pub fn current_branch(dir: &Path) -> Result<Option<String>, Report> {
+    match output(dir, &["symbolic-ref", "--short", "-q", "HEAD"]) {
+        Ok(branch) => Ok(Some(branch)),
+        Err(_) => {
+            // make sure it is a repository and HEAD is detached
+            output(dir, &["rev-parse", "--git-dir"])?;
+            Ok(None)
+        }
+    }
+}
+
+// Whether the working tree has uncommitted changes or untracked files
+pub fn is_dirty(dir: &Path) -> Result<bool, Report> {
+    Ok(!output(dir, &["status", "--porcelain"])?.is_empty())
+}
+
+// Get the upstream of current branch. eg. origin/main, return None if no upstream
+pub fn upstream(dir: &Path) -> Option<String> {
+    output(dir, &["rev-parse", "--abbrev-ref", "@{upstream}"]).ok()
+}
+
+// Get the commit id of the revision, return None if not exist
+pub fn rev_parse(dir: &Path, rev: &str) -> Option<String> {
+    output(dir, &["rev-parse", "--verify", "-q", rev]).ok()
+}
+
+// Get the remote-tracking branches and tags with the commit id. eg. refs/remotes/origin/main
+// The symbolic refs like refs/remotes/origin/HEAD are excluded.
+pub fn remote_refs(dir: &Path) -> Result<Vec<(String, String)>, Report> {
+    let refs = output(
+        dir,
+        &[
+            "for-each-ref",
+            "--format=%(refname) %(objectname) %(symref)",
+            "refs/remotes",
+            "refs/tags",
+        ],
+    )?;
+
+    Ok(refs
+        .lines()
+        .map(|line| line.split(' ').collect::<Vec<&str>>())
+        .filter(|fields| fields.len() >= 2 && fields.get(2).copied().unwrap_or_default().is_empty())
+        .map(|fields| (fields[0].to_string(), fields[1].to_string()))
+        .collect())
+}
+
+// Whether the commit is the ancestor of another commit
+pub fn is_ancestor(dir: &Path, ancestor: &str, commit: &str) -> bool {
+    output(dir, &["merge-base", "--is-ancestor", ancestor, commit]).is_ok()
+}
+
+// Get the commit id and subject of the commits in range. eg. a..b
+pub fn log_subjects(dir: &Path, range: &str) -> Result<Vec<(String, String)>, Report> {
+    let log = output(dir, &["log", "--format=%H %s", range, "--"])?;
+
+    Ok(log
+        .lines()
+        .map(|line| line.split_once(' ').unwrap_or((line, "")))
+        .map(|(id, subject)| (id.to_string(), subject.to_string()))
+        .collect())
+}
+
+// Count of commits ahead and behind the upstream, return None if no upstream
+pub fn ahead_behind(dir: &Path) -> Result<Option<(u32, u32)>, Report> {
+    if upstream(dir).is_none() {
+        return Ok(None);
+    }
+
+    let counts = output(
+        dir,
+        &["rev-list", "--left-right", "--count", "HEAD...@{upstream}"],
+    )?;
+
+    let mut iter = counts.split_whitespace().map(|s| s.parse::<u32>());
+
+    match (iter.next(), iter.next()) {
+        (Some(Ok(ahead)), Some(Ok(behind))) => Ok(Some((ahead, behind))),
+        _ => Err(Report::msg(format!("invalid rev-list output '{}'", counts))),
+    }
+}
+
+// Count of the stash entries
+pub fn stash_count(dir: &Path) -> Result<u32, Report> {
+    Ok(output(dir, &["stash", "list"])?.lines().count() as u32)
+}
+
+// Count of the commits in local branches which are not in any remote, return None if no remote
+pub fn unpushed_count(dir: &Path) -> Result<Option<u32>, Report> {
+    if output(dir, &["remote"])?.is_empty() {
+        return Ok(None);
+    }
+
+    let count = output(
+        dir,
+        &["rev-list", "--count", "--branches", "--not", "--remotes"],
+    )?;
+
+    Ok(Some(count.parse::<u32>()?))
+}
+
+// The local branches with the count of commits which are not in any remote
+pub fn unpushed_branches(dir: &Path) -> Result<Vec<(String, u32)>, Report> {
+    let mut branches: Vec<(String, u32)> = vec![];
+
+    for branch in output(
+        dir,
+        &["for-each-ref", "--format=%(refname:short)", "refs/heads"],
+    )?
+    .lines()
     {
-        Ok(mut child) => match child.wait() {
-            Ok(state) => {
-                if state.success() {
-                    Ok(())
-                } else {
-                    Err(Report::msg("git clone process fail"))
-                }
-            }
-            Err(e) => Err(Report::from(e)),
-        },
-        Err(e) => Err(Report::from(e)),
+        let count = output(
+            dir,
+            &[
+                "rev-list",
+                "--count",
+                &format!("refs/heads/{}", branch),
+                "--not",
+                "--remotes",
+            ],
+        )?
+        .parse::<u32>()?;
+
+        if count > 0 {
+            branches.push((branch.to_string(), count));
+        }
+    }
+
+    Ok(branches)
+}
+
+// Get the date (ISO 8601) and author of the last commit, return None if no commit
+pub fn last_commit(dir: &Path) -> Result<Option<(String, String)>, Report> {
+    if output(dir, &["rev-parse", "--verify", "-q", "HEAD"]).is_err() {
+        return Ok(None);
     }
+
+    let log = output(dir, &["log", "-1", "--format=%cI%x00%an"])?;
+
+    match log.split_once('\0') {
+        Some((date, author)) => Ok(Some((date.to_string(), author.to_string()))),
+        None => Err(Report::msg(format!("invalid log output '{}'", log))),
+    }
+}
+
+// Get the url of the remote, return None if the remote does not exist
+pub fn remote_url(dir: &Path, remote: &str) -> Option<String> {
+    output(dir, &["remote", "get-url", remote]).ok()
 }
 
 #[cfg(test)]
 mod tests {
-    use crate::git;
+    use crate::{git, network, testing};
     use std::{env, fs, path::Path};
 
     #[test]
@@ -238,6 +443,7 @@ mod tests {
         let r1 = git::url_to_path(".", url1);
 
         assert!(r1.is_err());
+        assert!(git::url_to_path(".", "axetroy/gpm.rs").is_err());
     }
 
     #[test]
@@ -271,11 +477,73 @@ mod tests {
 
         let dest_dir = Path::new("./dist");
 
-        let r1 = git::clone(url1, dest_dir, vec![]);
+        let r1 = git::clone(url1, dest_dir, vec![], &network::Options::default());
 
         assert!(r1.is_ok());
         assert!(dest_dir.exists());
 
         fs::remove_dir_all(dest_dir).unwrap();
     }
+
+    #[test]
+    fn test_repository_state() {
+        let repo = testing::init_repo("git-state");
+
+        assert_eq!(
+            git::current_branch(&repo).unwrap(),
+            Some("main".to_string())
+        );
+        assert!(!git::is_dirty(&repo).unwrap());
+        assert_eq!(git::ahead_behind(&repo).unwrap(), None);
+        assert_eq!(git::remote_url(&repo, "origin"), None);
+
+        let (date, author) = git::last_commit(&repo).unwrap().unwrap();
+
+        assert!(!date.is_empty());
+        assert_eq!(author, "gpm");
+
+        fs::write(repo.join("new_file"), "content").unwrap();
+
+        assert!(git::is_dirty(&repo).unwrap());
+        assert_eq!(git::stash_count(&repo).unwrap(), 0);
+
+        testing::git(&repo, &["stash", "-u", "-q"]);
+
+        assert!(!git::is_dirty(&repo).unwrap());
+        assert_eq!(git::stash_count(&repo).unwrap(), 1);
+
+        fs::remove_dir_all(repo).unwrap();
+    }
+
+    #[test]
+    fn test_ahead_behind() {
+        let remote = testing::init_repo("git-ahead-behind-remote");
+        let repo = testing::clone_repo(&remote, "git-ahead-behind");
+
+        assert_eq!(git::ahead_behind(&repo).unwrap(), Some((0, 0)));
+
+        testing::commit(&repo, "local");
+        testing::commit(&remote, "remote");
+        git::output(&repo, &["fetch", "-q"]).unwrap();
+
+        assert_eq!(git::ahead_behind(&repo).unwrap(), Some((1, 1)));
+        assert_eq!(git::unpushed_count(&repo).unwrap(), Some(1));
+        assert_eq!(git::unpushed_count(&remote).unwrap(), None);
+        assert_eq!(
+            git::unpushed_branches(&repo).unwrap(),
+            vec![("main".to_string(), 1)]
+        );
+        // all the commits are unpushed without remote
+        assert_eq!(
+            git::unpushed_branches(&remote).unwrap(),
+            vec![("main".to_string(), 2)]
+        );
+        assert_eq!(
+            git::remote_url(&repo, "origin"),
+            Some(remote.to_str().unwrap().to_string())
+        );
+
+        fs::remove_dir_all(remote).unwrap();
+        fs::remove_dir_all(repo).unwrap();
+    }
 }
@@ -0,0 +1,268 @@
+#![deny(warnings)]
+
+use crate::filter::Pattern;
+use crate::format::Output;
+use crate::git;
+use crate::repository::Repository;
+use eyre::Report;
+use regex::{Regex, RegexBuilder};
+use serde_json::json;
+use std::fs;
+use std::path::Path;
+
+// Only the beginning of file is checked for binary, same as git
+const BINARY_CHECK_SIZE: usize = 8000;
+
+#[derive(Debug, Clone)]
+pub struct Options {
+    pub regex: Regex,
+    // the file matches any of the globs, match the file name if the glob has no '/'
+    pub globs: Vec<(Pattern, bool)>,
+    // the count of lines printed before and after the matched line
+    pub context: usize,
+}
+
+// The matched line of file
+#[derive(Debug, Clone, PartialEq)]
+pub struct Match {
+    // the path relative to the repository
+    pub file: String,
+    // start from 1
+    pub line: usize,
+    pub text: String,
+    pub before: Vec<String>,
+    pub after: Vec<String>,
+}
+
+impl Options {
+    pub fn new(
+        pattern: &str,
+        fixed_strings: bool,
+        ignore_case: bool,
+        globs: &[&str],
+        context: usize,
+    ) -> Result<Options, Report> {
+        let pattern = if fixed_strings {
+            regex::escape(pattern)
+        } else {
+            pattern.to_string()
+        };
+
+        Ok(Options {
+            regex: RegexBuilder::new(&pattern)
+                .case_insensitive(ignore_case)
+                .build()?,
+            globs: globs
+                .iter()
+                .map(|g| Ok((Pattern::parse(g)?, g.contains('/'))))
+                .collect::<Result<Vec<(Pattern, bool)>, Report>>()?,
+            context,
+        })
+    }
+
+    fn is_file_match(&self, file: &str) -> bool {
+        if self.globs.is_empty() {
+            return true;
+        }
+
+        let name = file.rsplit('/').next().unwrap_or(file);
+
+        self.globs
+            .iter()
+            .any(|(glob, with_path)| glob.is_match(if *with_path { file } else { name }))
+    }
+}
+
+// Search the text, return the matched lines with context
+pub fn search_text(file: &str, content: &str, options: &Options) -> Vec<Match> {
+    let lines = content.lines().collect::<Vec<&str>>();
+
+    lines
+        .iter()
+        .enumerate()
+        .filter(|(_, line)| options.regex.is_match(line))
+        .map(|(index, line)| {
+            let start = index.saturating_sub(options.context);
+            let end = (index + 1 + options.context).min(lines.len());
+
+            Match {
+                file: file.to_string(),
+                line: index + 1,
+                text: line.to_string(),
+                before: lines[start..index].iter().map(|s| s.to_string()).collect(),
+                after: lines[index + 1..end]
+                    .iter()
+                    .map(|s| s.to_string())
+                    .collect(),
+            }
+        })
+        .collect()
+}
+
+// Search the tracked files of the repository, the binary files and symlinks are skipped
+pub fn search(dir: &Path, options: &Options) -> Result<Vec<Match>, Report> {
+    if !git::is_repository(dir) {
+        return Ok(vec![]);
+    }
+
+    let files = git::output(dir, &["ls-files", "-z"])?;
+
+    let mut matches: Vec<Match> = vec![];
+
+    for file in files.split('\0').filter(|f| !f.is_empty()) {
+        if !options.is_file_match(file) {
+            continue;
+        }
+
+        let path = dir.join(file);
+
+        // the submodule is a folder, the deleted file does not exist
+        match fs::symlink_metadata(&path) {
+            Ok(metadata) if metadata.is_file() => {}
+            _ => continue,
+        }
+
+        let Ok(content) = fs::read(&path) else {
+            continue;
+        };
+
+        if content[..content.len().min(BINARY_CHECK_SIZE)].contains(&0) {
+            continue;
+        }
+
+        matches.extend(search_text(
+            file,
+            &String::from_utf8_lossy(&content),
+            options,
+        ));
+    }
+
+    Ok(matches)
+}
+
+// The plain text like grep, the context lines are separated by '-'
+pub fn lines(matches: &Match) -> Vec<String> {
+    let first = matches.line - matches.before.len();
+
+    matches
+        .before
+        .iter()
+        .enumerate()
+        .map(|(i, text)| format!("{}-{}-{}", matches.file, first + i, text))
+        .chain(std::iter::once(format!(
+            "{}:{}:{}",
+            matches.file, matches.line, matches.text
+        )))
+        .chain(
+            matches
+                .after
+                .iter()
+                .enumerate()
+                .map(|(i, text)| format!("{}-{}-{}", matches.file, matches.line + 1 + i, text)),
+        )
+        .collect()
+}
+
+// The structured output of matches
+pub fn output(matches: &[(Repository, Vec<Match>)]) -> Output {
+    let fields = Repository::FIELDS
+        .into_iter()
+        .chain(["file", "line", "text", "before", "after"])
+        .collect::<Vec<&str>>();
+
+    let mut output = Output::new("match", &fields).with_tree_levels(3);
+
+    for (repo, items) in matches {
+        for item in items {
+            let mut record = repo.fields();
+
+            record.extend([
+                json!(item.file),
+                json!(item.line),
+                json!(item.text),
+                json!(item.before),
+                json!(item.after),
+            ]);
+
+            output.push(record);
+        }
+    }
+
+    output
+}
+
+#[cfg(test)]
+mod tests {
+    use std::fs;
+
+    use crate::grep::{self, Options};
+    use crate::testing;
+
+    #[test]
+    fn test_search_text() {
+        let options = Options::new("foo", false, false, &[], 1).unwrap();
+
+        let r1 = grep::search_text("a.txt", "one\nfoo\ntwo\nthree\nfoo", &options);
+
+        assert_eq!(r1.len(), 2);
+        assert_eq!(r1[0].line, 2);
+        assert_eq!(r1[0].before, vec!["one"]);
+        assert_eq!(r1[0].after, vec!["two"]);
+        assert!(r1[1].after.is_empty());
+        assert_eq!(
+            grep::lines(&r1[0]),
+            vec!["a.txt-1-one", "a.txt:2:foo", "a.txt-3-two"]
+        );
+
+        let options = Options::new("a.c", true, true, &[], 0).unwrap();
+
+        assert_eq!(grep::search_text("a.txt", "A.C\nabc", &options).len(), 1);
+    }
+
+    #[test]
+    fn test_search_tracked_files() {
+        let repo = testing::init_repo("grep-repo");
+
+        fs::create_dir_all(repo.join("src")).unwrap();
+        fs::write(repo.join("src").join("main.rs"), "fn use_api() {}\n").unwrap();
+        fs::write(repo.join("README.md"), "use_api\n").unwrap();
+        fs::write(repo.join("binary"), b"use_api\0").unwrap();
+        fs::write(repo.join(".gitignore"), "ignored.rs\n").unwrap();
+        testing::git(&repo, &["add", "-A"]);
+        testing::git(&repo, &["commit", "-q", "-m", "files"]);
+
+        // the ignored and untracked files are not searched
+        fs::write(repo.join("ignored.rs"), "use_api\n").unwrap();
+        fs::write(repo.join("untracked.rs"), "use_api\n").unwrap();
+
+        let r1 = grep::search(
+            &repo,
+            &Options::new("use_\\w+", false, false, &[], 0).unwrap(),
+        )
+        .unwrap()
+        .into_iter()
+        .map(|m| m.file)
+        .collect::<Vec<String>>();
+
+        assert_eq!(r1, vec!["README.md", "src/main.rs"]);
+
+        let r2 = grep::search(
+            &repo,
+            &Options::new("use_api", false, false, &["*.rs"], 0).unwrap(),
+        )
+        .unwrap();
+
+        assert_eq!(r2.len(), 1);
+        assert_eq!(r2[0].file, "src/main.rs");
+
+        let r3 = grep::search(
+            &repo,
+            &Options::new("use_api", false, false, &["docs/*"], 0).unwrap(),
+        )
+        .unwrap();
+
+        assert!(r3.is_empty());
+
+        fs::remove_dir_all(repo).unwrap();
+    }
+}
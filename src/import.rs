@@ -0,0 +1,385 @@
+#![deny(warnings)]
+
+use crate::clone::Conflict;
+use crate::format::Output;
+use crate::git;
+use crate::staging;
+use crate::trash;
+use crate::util;
+use eyre::Report;
+use serde_json::json;
+use std::fs;
+use std::io;
+use std::path::{Path, PathBuf};
+
+// How to put the repository into the layout of root
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Mode {
+    Move,
+    // keep the repository in place and link it into the layout
+    Symlink,
+}
+
+impl Mode {
+    pub const NAMES: [&'static str; 2] = ["move", "symlink"];
+
+    pub fn parse(name: &str) -> Result<Mode, Report> {
+        match name {
+            "move" => Ok(Mode::Move),
+            "symlink" => Ok(Mode::Symlink),
+            _ => Err(Report::msg(format!("invalid mode '{}'", name))),
+        }
+    }
+}
+
+#[derive(Debug, Clone)]
+pub struct Options {
+    pub mode: Mode,
+    pub conflict: Conflict,
+    pub dry_run: bool,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub enum ImportResult {
+    Imported(PathBuf),
+    // the repository would be imported without dry-run
+    Planned(PathBuf),
+    Skipped(String),
+    // the repository has no origin, it can not be put into the layout
+    NoRemote,
+    Failed(String),
+}
+
+impl ImportResult {
+    pub const NAMES: [&'static str; 5] = ["imported", "planned", "skipped", "no-remote", "failed"];
+
+    pub fn name(&self) -> &'static str {
+        match self {
+            ImportResult::Imported(_) => "imported",
+            ImportResult::Planned(_) => "planned",
+            ImportResult::Skipped(_) => "skipped",
+            ImportResult::NoRemote => "no-remote",
+            ImportResult::Failed(_) => "failed",
+        }
+    }
+
+    pub fn detail(&self) -> Option<String> {
+        match self {
+            ImportResult::Imported(path) | ImportResult::Planned(path) => {
+                Some(path.display().to_string())
+            }
+            ImportResult::Skipped(s) | ImportResult::Failed(s) => Some(s.to_string()),
+            ImportResult::NoRemote => None,
+        }
+    }
+}
+
+// Find the git repositories in the folder, the nested repositories and the hidden or linked folders are not walked
+pub fn discover(dir: &Path) -> io::Result<Vec<PathBuf>> {
+    let mut repositories: Vec<PathBuf> = vec![];
+
+    if git::is_repository(dir) {
+        repositories.push(dir.to_path_buf());
+        return Ok(repositories);
+    }
+
+    let mut entries = fs::read_dir(dir)?
+        .flatten()
+        .map(|e| e.path())
+        .collect::<Vec<PathBuf>>();
+
+    entries.sort();
+
+    for path in entries {
+        let is_dir = path.symlink_metadata().map(|m| m.is_dir()).unwrap_or(false);
+
+        let is_hidden = path
+            .file_name()
+            .is_some_and(|n| n.to_string_lossy().starts_with('.'));
+
+        if !is_dir || is_hidden {
+            continue;
+        }
+
+        // the folder may be not readable, skip it instead of stopping the walk
+        if let Ok(found) = discover(&path) {
+            repositories.extend(found);
+        }
+    }
+
+    Ok(repositories)
+}
+
+// Move or link the repository into the layout of root
+fn put(source: &Path, dest: &Path, mode: Mode) -> Result<(), Report> {
+    if let Some(parent) = dest.parent() {
+        fs::create_dir_all(parent)?;
+    }
+
+    match mode {
+        Mode::Move => fs::rename(source, dest).map_err(|e| {
+            Report::msg(format!(
+                "can not move to '{}', it may be on another disk: {}",
+                dest.display(),
+                e
+            ))
+        }),
+        Mode::Symlink => {
+            #[cfg(unix)]
+            std::os::unix::fs::symlink(source, dest)?;
+            #[cfg(windows)]
+            std::os::windows::fs::symlink_dir(source, dest)?;
+
+            Ok(())
+        }
+    }
+}
+
+// Import a repository into the layout of root
+pub fn import(root: &str, source: &Path, options: &Options) -> ImportResult {
+    let Some(remote) = git::remote_url(source, "origin") else {
+        return ImportResult::NoRemote;
+    };
+
+    let mut dest = match git::url_to_path(root, &remote) {
+        Ok(dest) => dest,
+        Err(e) => return ImportResult::Failed(format!("invalid origin '{}': {}", remote, e)),
+    };
+
+    // the root may contain a symlink, the repository must never override itself
+    let resolved = fs::canonicalize(&dest).ok();
+
+    if dest == source || (resolved.is_some() && resolved == fs::canonicalize(source).ok()) {
+        return ImportResult::Skipped("already in the layout".to_string());
+    }
+
+    let mut locks = vec![];
+
+    match staging::lock_destination(root, &dest) {
+        Ok(Some(lock)) => locks.push(lock),
+        Ok(None) => return ImportResult::Skipped("another gpm is cloning it".to_string()),
+        Err(e) => return ImportResult::Failed(e.to_string()),
+    }
+
+    let mut override_existing = false;
+
+    // the broken link is also a conflict
+    if dest.symlink_metadata().is_ok() {
+        match options.conflict {
+            Conflict::Skip => {
+                return ImportResult::Skipped(format!("'{}' already exists", dest.display()))
+            }
+            Conflict::Override => override_existing = true,
+            Conflict::Auto => {
+                dest = util::find_available_path(dest);
+
+                match staging::lock_destination(root, &dest) {
+                    Ok(Some(lock)) => locks.push(lock),
+                    Ok(None) => {
+                        return ImportResult::Skipped("another gpm is cloning it".to_string())
+                    }
+                    Err(e) => return ImportResult::Failed(e.to_string()),
+                }
+            }
+        }
+    }
+
+    if options.dry_run {
+        return ImportResult::Planned(dest);
+    }
+
+    if override_existing {
+        if let Err(e) = trash::trash(root, &dest) {
+            return ImportResult::Failed(format!("can not move the existing to trash: {}", e));
+        }
+    }
+
+    if let Err(e) = put(source, &dest, options.mode) {
+        return ImportResult::Failed(e.to_string());
+    }
+
+    ImportResult::Imported(dest)
+}
+
+// The structured output of results
+pub fn output(sources: &[PathBuf], results: &[ImportResult]) -> Output {
+    let mut output = Output::new("import", &["source", "result", "path", "detail"]);
+
+    for (source, result) in sources.iter().zip(results.iter()) {
+        let (path, detail) = match result {
+            ImportResult::Imported(path) | ImportResult::Planned(path) => {
+                (Some(path.display().to_string()), None)
+            }
+            result => (None, result.detail()),
+        };
+
+        output.push(vec![
+            json!(source.display().to_string()),
+            json!(result.name()),
+            json!(path),
+            json!(detail),
+        ]);
+    }
+
+    output
+}
+
+// The report of results, the repositories without remote are listed separately at the end
+pub fn report(sources: &[PathBuf], results: &[ImportResult]) -> Vec<String> {
+    let mut lines: Vec<String> = vec![];
+
+    for (source, result) in sources.iter().zip(results.iter()) {
+        if *result != ImportResult::NoRemote {
+            lines.push(match result.detail() {
+                Some(detail) => format!("{:10} {} -> {}", result.name(), source.display(), detail),
+                None => format!("{:10} {}", result.name(), source.display()),
+            });
+        }
+    }
+
+    lines.push(
+        ImportResult::NAMES
+            .iter()
+            .map(|name| {
+                let count = results.iter().filter(|r| r.name() == *name).count();
+                format!("{} {}", count, name)
+            })
+            .collect::<Vec<String>>()
+            .join(", "),
+    );
+
+    let no_remote = sources
+        .iter()
+        .zip(results.iter())
+        .filter(|(_, r)| **r == ImportResult::NoRemote)
+        .map(|(source, _)| format!("  {}", source.display()))
+        .collect::<Vec<String>>();
+
+    if !no_remote.is_empty() {
+        lines.push("The repositories without origin are not imported:".to_string());
+        lines.extend(no_remote);
+    }
+
+    lines
+}
+
+#[cfg(test)]
+mod tests {
+    use std::fs;
+
+    use crate::clone::Conflict;
+    use crate::import::{self, ImportResult, Mode, Options};
+    use crate::testing;
+
+    #[test]
+    fn test_discover_and_import() {
+        let code = testing::temp_dir("import-code");
+        let root = testing::temp_dir("import-root");
+        let root_str = root.to_str().unwrap();
+
+        let a = code.join("work").join("a");
+        let b = code.join("b");
+        let local = code.join("local");
+
+        for (dir, remote) in [
+            (&a, Some("https://github.com/axetroy/a.git")),
+            (&b, Some("git@github.com:axetroy/b.git")),
+            (&local, None),
+        ] {
+            fs::create_dir_all(dir).unwrap();
+            testing::git(dir, &["init", "-q", "-b", "main"]);
+
+            if let Some(remote) = remote {
+                testing::git(dir, &["remote", "add", "origin", remote]);
+            }
+        }
+
+        // the nested repository is not walked
+        fs::create_dir_all(a.join("vendor").join(".git")).unwrap();
+
+        assert_eq!(
+            import::discover(&code).unwrap(),
+            vec![b.clone(), local.clone(), a.clone()]
+        );
+
+        let mut options = Options {
+            mode: Mode::Move,
+            conflict: Conflict::Skip,
+            dry_run: true,
+        };
+
+        let dest_a = root.join("github.com").join("axetroy").join("a");
+        let dest_b = root.join("github.com").join("axetroy").join("b");
+
+        assert_eq!(
+            import::import(root_str, &a, &options),
+            ImportResult::Planned(dest_a.clone())
+        );
+        assert_eq!(
+            import::import(root_str, &local, &options),
+            ImportResult::NoRemote
+        );
+        assert!(a.exists());
+
+        options.dry_run = false;
+
+        assert_eq!(
+            import::import(root_str, &a, &options),
+            ImportResult::Imported(dest_a.clone())
+        );
+        assert!(!a.exists());
+        assert!(dest_a.join(".git").exists());
+
+        options.mode = Mode::Symlink;
+
+        assert_eq!(
+            import::import(root_str, &b, &options),
+            ImportResult::Imported(dest_b.clone())
+        );
+        assert!(b.exists());
+        assert!(dest_b.join(".git").exists());
+
+        assert!(matches!(
+            import::import(root_str, &b, &options),
+            ImportResult::Skipped(_)
+        ));
+
+        // the repository in the layout is imported again by the linked root
+        #[cfg(unix)]
+        {
+            let link = code.join("root-link");
+
+            std::os::unix::fs::symlink(&root, &link).unwrap();
+
+            options.mode = Mode::Move;
+            options.conflict = Conflict::Override;
+
+            assert_eq!(
+                import::import(link.to_str().unwrap(), &dest_a, &options),
+                ImportResult::Skipped("already in the layout".to_string())
+            );
+            assert!(dest_a.join(".git").exists());
+        }
+
+        let sources = vec![b.clone(), local.clone()];
+        let report = import::report(
+            &sources,
+            &[
+                ImportResult::Skipped("exists".to_string()),
+                ImportResult::NoRemote,
+            ],
+        );
+
+        assert_eq!(
+            report[1..],
+            [
+                "0 imported, 0 planned, 1 skipped, 1 no-remote, 0 failed".to_string(),
+                "The repositories without origin are not imported:".to_string(),
+                format!("  {}", local.display()),
+            ]
+        );
+
+        fs::remove_dir_all(code).unwrap();
+        fs::remove_dir_all(root).unwrap();
+    }
+}
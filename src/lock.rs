@@ -0,0 +1,134 @@
+#![deny(warnings)]
+
+use eyre::Report;
+use fs2::FileExt;
+use std::fs::{self, File, OpenOptions};
+use std::path::{Path, PathBuf};
+use std::process;
+
+// The advisory lock of file, released when dropped.
+// The lock file is kept after released, removing it may break the process waiting for it.
+pub struct FileLock {
+    _file: File,
+}
+
+fn open(path: &Path) -> Result<File, Report> {
+    if let Some(parent) = path.parent() {
+        fs::create_dir_all(parent)?;
+    }
+
+    Ok(OpenOptions::new()
+        .read(true)
+        .write(true)
+        .create(true)
+        .truncate(false)
+        .open(path)?)
+}
+
+// Wait until the lock is acquired
+pub fn lock(path: &Path) -> Result<FileLock, Report> {
+    let file = open(path)?;
+
+    file.lock_exclusive()?;
+
+    Ok(FileLock { _file: file })
+}
+
+// Acquire the lock without waiting, return None if it is held by another
+pub fn try_lock(path: &Path) -> Result<Option<FileLock>, Report> {
+    let file = open(path)?;
+
+    match file.try_lock_exclusive() {
+        Ok(()) => Ok(Some(FileLock { _file: file })),
+        Err(e) if e.kind() == fs2::lock_contended_error().kind() => Ok(None),
+        Err(e) => Err(e.into()),
+    }
+}
+
+// The lock file of the path. eg. ~/.gpmrc.lock
+pub fn lock_path(path: &Path) -> PathBuf {
+    let mut lock_path = path.as_os_str().to_owned();
+    lock_path.push(".lock");
+    PathBuf::from(lock_path)
+}
+
+// Write the file to a temp file beside it and rename, the reader never sees a partial file.
+// The target of symlink is written and its permissions are kept. eg. ~/.gpmrc linked to the dotfiles
+pub fn write_atomic(path: &Path, content: &str) -> Result<(), Report> {
+    let path = fs::canonicalize(path).unwrap_or_else(|_| path.to_path_buf());
+
+    let mut temp = path.as_os_str().to_owned();
+    temp.push(format!(".{}.tmp", process::id()));
+    let temp = PathBuf::from(temp);
+
+    let result = fs::write(&temp, content)
+        .and_then(|_| match fs::metadata(&path) {
+            Ok(metadata) => fs::set_permissions(&temp, metadata.permissions()),
+            Err(_) => Ok(()),
+        })
+        .and_then(|_| fs::rename(&temp, &path));
+
+    if let Err(e) = result {
+        fs::remove_file(&temp).ok();
+        return Err(e.into());
+    }
+
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use std::fs;
+
+    use crate::lock;
+    use crate::testing;
+
+    #[test]
+    fn test_lock() {
+        let dir = testing::temp_dir("lock");
+        let path = dir.join("a").join("file.lock");
+
+        let l1 = lock::try_lock(&path).unwrap();
+
+        assert!(l1.is_some());
+        assert!(lock::try_lock(&path).unwrap().is_none());
+
+        drop(l1);
+
+        assert!(lock::try_lock(&path).unwrap().is_some());
+        assert_eq!(
+            lock::lock_path(&dir.join(".gpmrc")),
+            dir.join(".gpmrc.lock")
+        );
+
+        lock::write_atomic(&dir.join("file"), "content").unwrap();
+
+        assert_eq!(fs::read_to_string(dir.join("file")).unwrap(), "content");
+        assert_eq!(fs::read_dir(&dir).unwrap().count(), 2);
+
+        // the symlink and the permissions are kept
+        #[cfg(unix)]
+        {
+            use std::os::unix::fs::PermissionsExt;
+
+            let link = dir.join("link");
+
+            fs::set_permissions(dir.join("file"), fs::Permissions::from_mode(0o600)).unwrap();
+            std::os::unix::fs::symlink(dir.join("file"), &link).unwrap();
+
+            lock::write_atomic(&link, "updated").unwrap();
+
+            assert!(fs::symlink_metadata(&link)
+                .unwrap()
+                .file_type()
+                .is_symlink());
+            assert_eq!(fs::read_to_string(dir.join("file")).unwrap(), "updated");
+            assert_eq!(
+                fs::metadata(dir.join("file")).unwrap().permissions().mode() & 0o777,
+                0o600
+            );
+        }
+
+        fs::remove_dir_all(dir).unwrap();
+    }
+}
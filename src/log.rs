@@ -0,0 +1,464 @@
+#![deny(warnings)]
+
+use crate::format::Output;
+use crate::git;
+use crate::repository::Repository;
+use clap::{Arg, ArgMatches};
+use eyre::Report;
+use serde_json::json;
+use std::path::Path;
+
+#[derive(Debug, Clone, Default)]
+pub struct Options {
+    // the date accepted by git. eg. yesterday, 2 weeks ago, 2023-01-01
+    pub since: Option<String>,
+    pub until: Option<String>,
+    // 'me' means the user.email of the repository
+    pub author: Option<String>,
+    // the glob of local branches, all the local branches if None
+    pub branch: Option<String>,
+    pub no_merges: bool,
+}
+
+// Search the commits by message, and by diff if `diff` is true
+#[derive(Debug, Clone, Default)]
+pub struct Search {
+    pub pattern: String,
+    pub diff: bool,
+    pub ignore_case: bool,
+}
+
+impl Options {
+    pub fn from_matches(matches: &ArgMatches) -> Options {
+        Options {
+            since: matches.value_of("since").map(|s| s.to_string()),
+            until: matches.value_of("until").map(|s| s.to_string()),
+            author: matches.value_of("author").map(|s| s.to_string()),
+            branch: matches.value_of("branch").map(|s| s.to_string()),
+            no_merges: matches.is_present("no-merges"),
+        }
+    }
+}
+
+// The arguments of the date range, author and merges for the commands read the commits
+pub fn args<'help>() -> Vec<Arg<'help>> {
+    vec![
+        Arg::new("since")
+            .long("since")
+            .help("The commits newer than the date. eg. yesterday, '2 weeks ago', 2023-01-01")
+            .takes_value(true),
+        Arg::new("until")
+            .long("until")
+            .help("The commits older than the date")
+            .takes_value(true),
+        Arg::new("author")
+            .long("author")
+            .help("The commits of the author, 'me' is the user.email of repository")
+            .takes_value(true),
+        Arg::new("branch")
+            .long("branch")
+            .help("The commits of the local branches match the glob, default to all the local branches")
+            .takes_value(true),
+        Arg::new("no-merges")
+            .long("no-merges")
+            .help("Do not read the merge commits")
+            .takes_value(false),
+    ]
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct Commit {
+    pub id: String,
+    pub timestamp: i64,
+    // ISO 8601 author date
+    pub date: String,
+    pub author: String,
+    pub email: String,
+    pub subject: String,
+}
+
+impl Commit {
+    // The date for printing. eg. 2023-01-01 12:00
+    pub fn short_date(&self) -> String {
+        self.date
+            .chars()
+            .take(16)
+            .collect::<String>()
+            .replace('T', " ")
+    }
+
+    pub fn short_id(&self) -> &str {
+        &self.id[..7.min(self.id.len())]
+    }
+}
+
+// The arguments of git log to limit the commits by the options
+pub fn range_args(dir: &Path, options: &Options) -> Result<Vec<String>, Report> {
+    let mut args: Vec<String> = vec![match &options.branch {
+        Some(branch) => format!("--branches={}", branch),
+        None => "--branches".to_string(),
+    }];
+
+    if let Some(since) = &options.since {
+        args.push(format!("--since={}", since));
+    }
+
+    if let Some(until) = &options.until {
+        args.push(format!("--until={}", until));
+    }
+
+    if let Some(author) = &options.author {
+        let author = if author == "me" {
+            git::output(dir, &["config", "user.email"])
+                .map_err(|_| Report::msg("can not resolve 'me', the user.email is not set"))?
+        } else {
+            author.to_string()
+        };
+
+        args.push(format!("--author={}", author));
+    }
+
+    if options.no_merges {
+        args.push("--no-merges".to_string());
+    }
+
+    Ok(args)
+}
+
+// Collect the commits of the local branches in the repository, the newest first
+pub fn collect(dir: &Path, options: &Options) -> Result<Vec<Commit>, Report> {
+    collect_with(dir, options, &[])
+}
+
+// Collect the commits match the pattern in message or diff, the newest first
+pub fn search(dir: &Path, options: &Options, search: &Search) -> Result<Vec<Commit>, Report> {
+    let mut flags = vec!["--extended-regexp".to_string()];
+
+    if search.ignore_case {
+        flags.push("--regexp-ignore-case".to_string());
+    }
+
+    let mut commits = collect_with(
+        dir,
+        options,
+        &[flags.clone(), vec![format!("--grep={}", search.pattern)]].concat(),
+    )?;
+
+    // git can not match the message or diff in one log
+    if search.diff {
+        for commit in collect_with(
+            dir,
+            options,
+            &[flags, vec!["-G".to_string(), search.pattern.clone()]].concat(),
+        )? {
+            if !commits.iter().any(|c| c.id == commit.id) {
+                commits.push(commit);
+            }
+        }
+
+        commits.sort_by_key(|c| std::cmp::Reverse(c.timestamp));
+    }
+
+    Ok(commits)
+}
+
+fn collect_with(dir: &Path, options: &Options, extra: &[String]) -> Result<Vec<Commit>, Report> {
+    if !git::is_repository(dir) || git::rev_parse(dir, "HEAD").is_none() {
+        return Ok(vec![]);
+    }
+
+    let mut args: Vec<String> = vec![
+        "log".to_string(),
+        "--format=%H%x00%at%x00%aI%x00%an%x00%ae%x00%s".to_string(),
+    ];
+
+    args.extend(range_args(dir, options)?);
+    args.extend(extra.iter().cloned());
+
+    let log = git::output(dir, &args.iter().map(|s| s.as_str()).collect::<Vec<&str>>())?;
+
+    Ok(log
+        .lines()
+        .filter_map(|line| {
+            let fields = line.splitn(6, '\0').collect::<Vec<&str>>();
+
+            if fields.len() < 5 {
+                return None;
+            }
+
+            Some(Commit {
+                id: fields[0].to_string(),
+                timestamp: fields[1].parse().unwrap_or(0),
+                date: fields[2].to_string(),
+                author: fields[3].to_string(),
+                email: fields[4].to_string(),
+                subject: fields.get(5).unwrap_or(&"").to_string(),
+            })
+        })
+        .collect())
+}
+
+// Merge the commits of repositories in chronological order, the newest first
+pub fn chronological(commits: &[(Repository, Vec<Commit>)]) -> Vec<(&Repository, &Commit)> {
+    let mut merged = commits
+        .iter()
+        .flat_map(|(repo, list)| list.iter().map(move |c| (repo, c)))
+        .collect::<Vec<_>>();
+
+    merged.sort_by_key(|(_, c)| std::cmp::Reverse(c.timestamp));
+
+    merged
+}
+
+fn title(repo: &Repository) -> String {
+    format!("{}/{}/{}", repo.host, repo.owner, repo.name)
+}
+
+// The Markdown report for pasting. eg. standup or timesheet
+pub fn markdown(commits: &[(Repository, Vec<Commit>)], merged: bool) -> String {
+    let mut lines: Vec<String> = vec![];
+
+    if merged {
+        for (repo, commit) in chronological(commits) {
+            lines.push(format!(
+                "- {} **{}** `{}` {} ({})",
+                commit.short_date(),
+                title(repo),
+                commit.short_id(),
+                commit.subject,
+                commit.author
+            ));
+        }
+    } else {
+        for (repo, list) in commits {
+            if !lines.is_empty() {
+                lines.push(String::new());
+            }
+
+            lines.push(format!("## {}", title(repo)));
+            lines.push(String::new());
+
+            for commit in list {
+                lines.push(format!(
+                    "- {} `{}` {} ({})",
+                    commit.short_date(),
+                    commit.short_id(),
+                    commit.subject,
+                    commit.author
+                ));
+            }
+        }
+    }
+
+    lines.join("\n")
+}
+
+// The plain text for terminal
+pub fn text(commits: &[(Repository, Vec<Commit>)], merged: bool) -> String {
+    let mut lines: Vec<String> = vec![];
+
+    if merged {
+        for (repo, commit) in chronological(commits) {
+            lines.push(format!(
+                "{}  {}  {}  {}  {}",
+                commit.short_date(),
+                title(repo),
+                commit.short_id(),
+                commit.author,
+                commit.subject
+            ));
+        }
+    } else {
+        for (repo, list) in commits {
+            lines.push(repo.path_str().to_string());
+
+            for commit in list {
+                lines.push(format!(
+                    "    {}  {}  {}  {}",
+                    commit.short_date(),
+                    commit.short_id(),
+                    commit.author,
+                    commit.subject
+                ));
+            }
+        }
+    }
+
+    lines.join("\n")
+}
+
+// The structured output of commits
+pub fn output(commits: &[(Repository, Vec<Commit>)], merged: bool) -> Output {
+    let fields = Repository::FIELDS
+        .into_iter()
+        .chain(["id", "date", "author", "email", "subject"])
+        .collect::<Vec<&str>>();
+
+    let mut output = Output::new("commit", &fields).with_tree_levels(3);
+
+    let items = if merged {
+        chronological(commits)
+    } else {
+        commits
+            .iter()
+            .flat_map(|(repo, list)| list.iter().map(move |c| (repo, c)))
+            .collect()
+    };
+
+    for (repo, commit) in items {
+        let mut record = repo.fields();
+
+        record.extend([
+            json!(commit.id),
+            json!(commit.date),
+            json!(commit.author),
+            json!(commit.email),
+            json!(commit.subject),
+        ]);
+
+        output.push(record);
+    }
+
+    output
+}
+
+#[cfg(test)]
+mod tests {
+    use std::fs;
+
+    use crate::log::{self, Options, Search};
+    use crate::repository::Repository;
+    use crate::testing;
+
+    #[test]
+    fn test_collect_commits() {
+        let repo = testing::init_repo("log-repo");
+
+        testing::git(&repo, &["config", "user.email", "gpm@example.com"]);
+        testing::git(&repo, &["checkout", "-q", "-b", "feature"]);
+        testing::commit(&repo, "feature");
+        testing::git(&repo, &["checkout", "-q", "main"]);
+
+        let r1 = log::collect(&repo, &Options::default()).unwrap();
+
+        assert_eq!(
+            r1.iter().map(|c| c.subject.as_str()).collect::<Vec<&str>>(),
+            vec!["feature", "init"]
+        );
+        assert_eq!(r1[0].author, "gpm");
+        assert_eq!(r1[0].email, "gpm@example.com");
+
+        let r2 = log::collect(
+            &repo,
+            &Options {
+                author: Some("me".to_string()),
+                since: Some("1 hour ago".to_string()),
+                ..Options::default()
+            },
+        )
+        .unwrap();
+
+        assert_eq!(r2.len(), 2);
+
+        let r3 = log::collect(
+            &repo,
+            &Options {
+                author: Some("someone-else".to_string()),
+                ..Options::default()
+            },
+        )
+        .unwrap();
+
+        assert!(r3.is_empty());
+
+        let r4 = log::collect(
+            &repo,
+            &Options {
+                until: Some("2000-01-01".to_string()),
+                ..Options::default()
+            },
+        )
+        .unwrap();
+
+        assert!(r4.is_empty());
+
+        let commits = vec![(Repository::new("/", repo.clone()), r1.clone())];
+
+        let md = log::markdown(&commits, false);
+
+        assert!(md.starts_with("## "));
+        assert!(md.contains(&format!("`{}` feature (gpm)", r1[0].short_id())));
+
+        fs::remove_dir_all(repo).unwrap();
+    }
+
+    #[test]
+    fn test_search_commits() {
+        let repo = testing::init_repo("log-search-repo");
+
+        testing::git(&repo, &["checkout", "-q", "-b", "feature"]);
+        testing::commit(&repo, "fix TICKET-42");
+        testing::git(&repo, &["checkout", "-q", "main"]);
+        fs::write(repo.join("init"), "call_api()").unwrap();
+        testing::git(&repo, &["commit", "-q", "-am", "update"]);
+
+        let search = |pattern: &str, diff: bool, branch: Option<&str>| {
+            log::search(
+                &repo,
+                &Options {
+                    branch: branch.map(|b| b.to_string()),
+                    ..Options::default()
+                },
+                &Search {
+                    pattern: pattern.to_string(),
+                    diff,
+                    ignore_case: true,
+                },
+            )
+            .unwrap()
+            .into_iter()
+            .map(|c| c.subject)
+            .collect::<Vec<String>>()
+        };
+
+        assert_eq!(search("ticket-[0-9]+", false, None), vec!["fix TICKET-42"]);
+        assert!(search("ticket-[0-9]+", false, Some("main")).is_empty());
+        assert!(search("call_api", false, None).is_empty());
+        assert_eq!(search("call_api", true, None), vec!["update"]);
+        assert_eq!(search("call_api|ticket", true, None).len(), 2);
+
+        fs::remove_dir_all(repo).unwrap();
+    }
+
+    #[test]
+    fn test_chronological() {
+        let commit = |timestamp: i64, subject: &str| log::Commit {
+            id: format!("{:040}", timestamp),
+            timestamp,
+            date: "2023-01-01T12:00:00+08:00".to_string(),
+            author: "gpm".to_string(),
+            email: "gpm@example.com".to_string(),
+            subject: subject.to_string(),
+        };
+
+        let r1 = Repository::new("/root", "/root/github.com/axetroy/a".into());
+        let r2 = Repository::new("/root", "/root/github.com/axetroy/b".into());
+
+        let commits = vec![
+            (r1, vec![commit(3, "a3"), commit(1, "a1")]),
+            (r2, vec![commit(2, "b2")]),
+        ];
+
+        let merged = log::chronological(&commits)
+            .iter()
+            .map(|(_, c)| c.subject.as_str())
+            .collect::<Vec<&str>>();
+
+        assert_eq!(merged, vec!["a3", "b2", "a1"]);
+
+        assert_eq!(
+            log::markdown(&commits, true).lines().next().unwrap(),
+            "- 2023-01-01 12:00 **github.com/axetroy/a** `0000000` a3 (gpm)"
+        );
+    }
+}
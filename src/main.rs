@@ -1,18 +1,50 @@
 #![deny(warnings)]
 
+mod audit;
+mod clone;
 mod configure;
+mod dedupe;
+mod doctor;
+mod exec;
 mod file_explorer;
+mod filter;
+mod format;
 mod git;
+mod grep;
+mod import;
+mod lock;
+mod log;
+mod manifest;
+mod migrate;
+mod network;
+mod news;
+mod parallel;
+mod progress;
+mod protocol;
+mod relocate;
+mod remote;
+mod remove;
+mod repository;
+mod staging;
+mod stats;
+mod status;
+mod sync;
+#[cfg(test)]
+mod testing;
+mod trash;
 mod util;
 mod vscode;
 mod walker;
 
 use clap::{arg, Arg, Command, PossibleValue};
+use format::{Format, Output};
 use inquire::{error::InquireError, Confirm, Select, Text};
+use repository::{Column, Metadata, Repository};
+use serde::Serialize;
+use serde_json::json;
 use std::collections::HashMap;
-use std::fs;
-use std::fs::File;
-use std::io::Write;
+use std::fs::{self, File};
+use std::io::{self, IsTerminal, Write};
 use std::path::Path;
 use std::path::PathBuf;
 use std::process;
@@ -36,10 +68,112 @@ fn select_folder(paths: Vec<PathBuf>, title: &str) -> Option<PathBuf> {
     }
 }
 
+#[derive(Serialize)]
+struct ListEntry<'a> {
+    path: &'a str,
+    #[serde(flatten)]
+    metadata: &'a Metadata,
+}
+
+// Parse the output format from arguments, exit if invalid
+fn output_format(matches: &clap::ArgMatches) -> Option<Format> {
+    Format::from_matches(matches).unwrap_or_else(|e| {
+        eprintln!("{}", e);
+        process::exit(0x1);
+    })
+}
+
+// The structured output of repositories with the columns
+fn repositories_output(
+    repositories: &[Repository],
+    metadata: &[Metadata],
+    columns: &[Column],
+) -> Output {
+    let fields = Repository::FIELDS
+        .into_iter()
+        .chain(columns.iter().map(|c| c.name()))
+        .collect::<Vec<&str>>();
+
+    let mut output = Output::new("repository", &fields).with_tree_levels(3);
+
+    for (repo, metadata) in repositories.iter().zip(metadata.iter()) {
+        let mut record = repo.fields();
+
+        record.extend(columns.iter().map(|c| metadata.json(*c)));
+
+        output.push(record);
+    }
+
+    output
+}
+
+fn print_trash(entries: &[trash::Entry], format: Option<Format>) {
+    match format {
+        Some(format) => trash::output(entries).print(&format),
+        None => {
+            for entry in entries {
+                println!(
+                    "{}  {}  {}",
+                    entry.id,
+                    entry.meta.path,
+                    entry.meta.remote.as_deref().unwrap_or("-")
+                );
+            }
+
+            if entries.is_empty() {
+                println!("The trash is empty");
+            }
+        }
+    }
+}
+
+// Find the repositories by the folder path, the query expression or the remote URL
+fn find_repositories(roots: &[String], query: &str) -> Result<Vec<Repository>, eyre::Report> {
+    let path = Path::new(query);
+
+    let repositories = if path.is_absolute() {
+        let repo = repository::locate(roots, path).ok_or_else(|| {
+            eyre::Report::msg(format!(
+                "'{}' is not a repository of the roots, expect <root>/<host>/<owner>/<name>",
+                query
+            ))
+        })?;
+
+        vec![repo]
+    } else if let Ok(expr) = filter::Expr::parse(query) {
+        filter::Filter::new(vec![expr]).apply(repository::scan(roots))
+    } else {
+        let mut repositories: Vec<Repository> = vec![];
+
+        for root in roots {
+            repositories.push(Repository::new(root, git::url_to_path(root, query)?));
+        }
+
+        repositories
+    };
+
+    Ok(repositories
+        .into_iter()
+        .filter(|r| r.path.is_dir())
+        .collect())
+}
+
 fn main() {
     let version = format!("v{}", env!("CARGO_PKG_VERSION"));
     let config_field_root = PossibleValue::new("root").help("The root of clones repository");
 
+    let host_jobs_arg = Arg::new("host-jobs")
+        .long("host-jobs")
+        .help("The max number of repositories of the same host to process in parallel")
+        .takes_value(true)
+        .validator(|v| match v.parse::<usize>() {
+            Ok(n) if n > 0 => Ok(()),
+            _ => Err(format!(
+                "invalid host jobs '{}', expect a positive number",
+                v
+            )),
+        });
+
     let mut app = Command::new("gpm")
         .version(version.as_str())
         .about(env!("CARGO_PKG_DESCRIPTION"))
@@ -51,13 +185,42 @@ fn main() {
         .subcommand(
             Command::new("clone")
                 .about("Clones repository")
-                .arg(arg!(<REMOTE> "The remote Git URL to clone"))
+                .arg(
+                    Arg::new("REMOTE")
+                        .help("The remote Git URL to clone, '-' to read the URLs from stdin")
+                        .multiple_values(true)
+                        .required_unless_present("from-file"),
+                )
                 .arg(
                     Arg::new("OPTIONS")
                         .required(false)
                         .multiple_occurrences(true)
+                        .last(true)
                         .help("The git clone flags. eg. --progress --recursive"),
                 )
+                .arg(
+                    Arg::new("from-file")
+                        .long("from-file")
+                        .help("Read the URLs from the file, one URL per line, the lines start with '#' are ignored")
+                        .takes_value(true),
+                )
+                .arg(
+                    Arg::new("on-conflict")
+                        .long("on-conflict")
+                        .help("What to do if the folder exists when clone many repositories, default to skip")
+                        .takes_value(true)
+                        .possible_values(clone::Conflict::NAMES),
+                )
+                .arg(
+                    Arg::new("strip-credentials")
+                        .long("strip-credentials")
+                        .help("Clone with the credentials in URL, but remove them from the origin of repository")
+                        .takes_value(false),
+                )
+                .arg(parallel::arg())
+                .arg(host_jobs_arg.clone())
+                .args(network::args())
+                .args(format::args())
                 .arg_required_else_help(true),
         )
         .subcommand(
@@ -69,10 +232,467 @@ fn main() {
                         .short('j')
                         .long("json")
                         .help("Print output as JSON format")
-                        .takes_value(false),
+                        .takes_value(false)
+                        .conflicts_with_all(&["format", "template"]),
+                )
+                .arg(
+                    Arg::new("columns")
+                        .short('c')
+                        .long("columns")
+                        .help("The columns of repository to print, separated by comma")
+                        .takes_value(true)
+                        .use_value_delimiter(true)
+                        .multiple_occurrences(true)
+                        .possible_values(Column::ALL.map(|c| c.name())),
                 )
+                .args(filter::args())
+                .args(format::args())
                 .about("List cloned repositories"),
         )
+        .subcommand(
+            Command::new("status")
+                .about("Print the repositories with uncommitted changes, unpushed commits, stashes or detached HEAD")
+                .arg(
+                    Arg::new("json")
+                        .short('j')
+                        .long("json")
+                        .help("Print output as JSON format")
+                        .takes_value(false)
+                        .conflicts_with_all(&["format", "template"]),
+                )
+                .args(filter::args())
+                .args(format::args()),
+        )
+        .subcommand(
+            Command::new("exec")
+                .about("Run command in each repository. eg. gpm exec --dirty -- git status")
+                .arg(
+                    Arg::new("COMMAND")
+                        .required(true)
+                        .multiple_values(true)
+                        .last(true)
+                        .help("The command to run, GPM_ROOT, GPM_REPO_HOST, GPM_REPO_OWNER, GPM_REPO_NAME and GPM_REPO_PATH are available in environment"),
+                )
+                .arg(parallel::arg())
+                .arg(
+                    Arg::new("output")
+                        .long("output")
+                        .help("Print the output with repository prefix line by line, or grouped by repository")
+                        .takes_value(true)
+                        .possible_values(exec::OutputMode::NAMES)
+                        .default_value("prefixed"),
+                )
+                .arg(
+                    Arg::new("fail-fast")
+                        .long("fail-fast")
+                        .help("Stop running in the remaining repositories when the command fail")
+                        .takes_value(false)
+                        .conflicts_with("keep-going"),
+                )
+                .arg(
+                    Arg::new("keep-going")
+                        .long("keep-going")
+                        .help("Keep running in the remaining repositories when the command fail, this is default")
+                        .takes_value(false),
+                )
+                .args(filter::args())
+                .args(format::args())
+                .arg_required_else_help(true),
+        )
+        .subcommand(
+            Command::new("fetch")
+                .about("Fetch all remotes of repositories")
+                .arg(parallel::arg())
+                .arg(host_jobs_arg.clone())
+                .args(network::args())
+                .args(filter::args())
+                .args(format::args()),
+        )
+        .subcommand(
+            Command::new("pull")
+                .about("Fetch and fast-forward the current branch of repositories, skip the dirty or detached one")
+                .arg(
+                    Arg::new("ff-only")
+                        .long("ff-only")
+                        .help("Only fast-forward, this is the only supported mode")
+                        .takes_value(false),
+                )
+                .arg(parallel::arg())
+                .arg(host_jobs_arg)
+                .args(network::args())
+                .args(filter::args())
+                .args(format::args()),
+        )
+        .subcommand(
+            Command::new("news")
+                .about("Print the incoming changes of the last fetch by gpm, including new commits, new tags and force-pushes")
+                .arg(
+                    Arg::new("since")
+                        .long("since")
+                        .help("Print the changes of all the fetches in the duration. eg. 7d")
+                        .takes_value(true),
+                )
+                .args(filter::args())
+                .args(format::args()),
+        )
+        .subcommand(
+            Command::new("log")
+                .about("Print the commits of all local branches of repositories in the date range")
+                .args(log::args())
+                .arg(
+                    Arg::new("chronological")
+                        .long("chronological")
+                        .help("Merge the commits of all repositories in chronological order instead of grouping by repository")
+                        .takes_value(false),
+                )
+                .arg(
+                    Arg::new("markdown")
+                        .long("markdown")
+                        .help("Print as Markdown for pasting into the standup or timesheet")
+                        .conflicts_with_all(&["format", "template"])
+                        .takes_value(false),
+                )
+                .args(filter::args())
+                .args(format::args()),
+        )
+        .subcommand(
+            Command::new("stats")
+                .about("Print the commits, lines added and removed and active days per author or repository in the date range")
+                .args(log::args())
+                .arg(
+                    Arg::new("by")
+                        .long("by")
+                        .help("Group the statistics by author or repository")
+                        .takes_value(true)
+                        .possible_values(["author", "repo"])
+                        .default_value("author"),
+                )
+                .args(filter::args())
+                .args(format::args()),
+        )
+        .subcommand(
+            Command::new("grep")
+                .about("Search the tracked files of repositories, the ignored and binary files are skipped")
+                .arg(arg!(<PATTERN> "The regular expression to search"))
+                .arg(
+                    Arg::new("ignore-case")
+                        .short('i')
+                        .long("ignore-case")
+                        .help("Search case insensitively")
+                        .takes_value(false),
+                )
+                .arg(
+                    Arg::new("fixed-strings")
+                        .short('F')
+                        .long("fixed-strings")
+                        .help("Treat the pattern as a literal string instead of regular expression")
+                        .takes_value(false),
+                )
+                .arg(
+                    Arg::new("glob")
+                        .short('g')
+                        .long("glob")
+                        .help("Only search the files match the glob or /regex/, the file name is matched if the glob has no '/'. eg. '*.rs'")
+                        .takes_value(true)
+                        .multiple_occurrences(true),
+                )
+                .arg(
+                    Arg::new("context")
+                        .short('C')
+                        .long("context")
+                        .help("Print the lines before and after the matched line")
+                        .takes_value(true)
+                        .default_value("0")
+                        .validator(|v| v.parse::<usize>().map(|_| ()).map_err(|e| e.to_string())),
+                )
+                .arg(parallel::arg())
+                .args(filter::args())
+                .args(format::args()),
+        )
+        .subcommand(
+            Command::new("search-commits")
+                .about("Search the commit messages of repositories, and the diffs with --diff")
+                .arg(arg!(<PATTERN> "The extended regular expression to search. eg. 'TICKET-[0-9]+'"))
+                .arg(
+                    Arg::new("ignore-case")
+                        .short('i')
+                        .long("ignore-case")
+                        .help("Search case insensitively")
+                        .takes_value(false),
+                )
+                .arg(
+                    Arg::new("diff")
+                        .long("diff")
+                        .help("Also search the added or removed lines of commits, same as 'git log -G'")
+                        .takes_value(false),
+                )
+                .args(log::args())
+                .arg(
+                    Arg::new("chronological")
+                        .long("chronological")
+                        .help("Merge the commits of all repositories in chronological order instead of grouping by repository")
+                        .takes_value(false),
+                )
+                .args(filter::args())
+                .args(format::args()),
+        )
+        .subcommand(
+            Command::new("rm")
+                .about("Remove repositories, refuse the one with uncommitted changes, untracked files, stashes or unpushed branches unless forced")
+                .arg(arg!(<QUERY> "The folder path, query expression or remote URL of repositories. eg. 'owner:axetroy and name:test-*'"))
+                .arg(
+                    Arg::new("force")
+                        .short('f')
+                        .long("force")
+                        .help("Remove the repositories even if the work would be lost")
+                        .takes_value(false),
+                )
+                .arg(
+                    Arg::new("dry-run")
+                        .long("dry-run")
+                        .help("Print what would be removed and lost without removing")
+                        .takes_value(false),
+                )
+                .arg(
+                    Arg::new("yes")
+                        .short('y')
+                        .long("yes")
+                        .help("Do not ask for confirmation")
+                        .takes_value(false),
+                )
+                .args(format::args()),
+        )
+        .subcommand(
+            Command::new("trash")
+                .about("The removed repositories are kept in the trash of root, print them if sub-command not provide")
+                .args(format::args())
+                .subcommand(
+                    Command::new("list")
+                        .about("Print the folders in trash")
+                        .args(format::args()),
+                )
+                .subcommand(
+                    Command::new("restore")
+                        .about("Move the folder in trash back to the original path")
+                        .arg(arg!(<ID_OR_PATH> "The id in trash, the original path or the remote URL, the newest one is restored if multiple matched"))
+                        .arg_required_else_help(true),
+                )
+                .subcommand(
+                    Command::new("purge")
+                        .about("Remove the folders in trash permanently")
+                        .arg(
+                            Arg::new("older-than")
+                                .long("older-than")
+                                .help("Only remove the folders moved to trash before the duration. eg. 30d")
+                                .takes_value(true),
+                        )
+                        .arg(
+                            Arg::new("yes")
+                                .short('y')
+                                .long("yes")
+                                .help("Do not ask for confirmation")
+                                .takes_value(false),
+                        ),
+                ),
+        )
+        .subcommand(
+            Command::new("export")
+                .about("Write the manifest of repositories, restore them on another machine with 'gpm restore'")
+                .arg(arg!([FILE] "The manifest file, the format is JSON if the extension is .json, otherwise TOML. Print to stdout if not provide"))
+                .arg(
+                    Arg::new("pin")
+                        .long("pin")
+                        .help("Pin the current commit of repositories")
+                        .takes_value(false),
+                )
+                .arg(
+                    Arg::new("json")
+                        .short('j')
+                        .long("json")
+                        .help("Print the manifest as JSON format")
+                        .takes_value(false),
+                )
+                .args(filter::args()),
+        )
+        .subcommand(
+            Command::new("restore")
+                .about("Clone the missing repositories of manifest and report the drift of workspace")
+                .arg(arg!(<MANIFEST> "The manifest file written by 'gpm export'"))
+                .arg(
+                    Arg::new("dry-run")
+                        .long("dry-run")
+                        .help("Print the missing and drifted repositories without cloning")
+                        .takes_value(false),
+                )
+                .arg(parallel::arg())
+                .args(network::args())
+                .args(format::args())
+                .arg_required_else_help(true),
+        )
+        .subcommand(
+            Command::new("import")
+                .about("Move the existing repositories into the layout of root by the origin URL")
+                .arg(
+                    Arg::new("DIR")
+                        .help("The folders to find the git repositories in")
+                        .multiple_values(true)
+                        .required(true),
+                )
+                .arg(
+                    Arg::new("mode")
+                        .long("mode")
+                        .help("Move the repositories, or keep them in place and link into the layout")
+                        .takes_value(true)
+                        .possible_values(import::Mode::NAMES)
+                        .default_value("move"),
+                )
+                .arg(
+                    Arg::new("on-conflict")
+                        .long("on-conflict")
+                        .help("What to do if the folder exists in the layout")
+                        .takes_value(true)
+                        .possible_values(clone::Conflict::NAMES)
+                        .default_value("skip"),
+                )
+                .arg(
+                    Arg::new("dry-run")
+                        .long("dry-run")
+                        .help("Print where the repositories would be put without moving")
+                        .takes_value(false),
+                )
+                .args(format::args())
+                .arg_required_else_help(true),
+        )
+        .subcommand(
+            Command::new("relocate")
+                .about("Move the repositories whose location does not match the origin. eg. the repository is renamed or transferred")
+                .arg(
+                    Arg::new("dry-run")
+                        .long("dry-run")
+                        .help("Print the mismatched repositories without moving")
+                        .takes_value(false),
+                )
+                .arg(
+                    Arg::new("yes")
+                        .short('y')
+                        .long("yes")
+                        .help("Do not ask for confirmation")
+                        .takes_value(false),
+                )
+                .args(filter::args())
+                .args(format::args()),
+        )
+        .subcommand(
+            Command::new("migrate-host")
+                .about("Rewrite the remotes of the old host to the new host, and move the repositories into the new host")
+                .arg(arg!(<OLD> "The old host. eg. git.oldcorp.com"))
+                .arg(arg!(<NEW> "The new host. eg. gitlab.newcorp.com"))
+                .arg(
+                    Arg::new("map-owner")
+                        .long("map-owner")
+                        .help("Remap the owner or group. eg. --map-owner old-team=new-group/team")
+                        .takes_value(true)
+                        .multiple_occurrences(true)
+                        .validator(|v| migrate::Rule::parse(v).map(|_| ())),
+                )
+                .arg(
+                    Arg::new("dry-run")
+                        .long("dry-run")
+                        .help("Print what would be changed without changing")
+                        .takes_value(false),
+                )
+                .arg(
+                    Arg::new("yes")
+                        .short('y')
+                        .long("yes")
+                        .help("Do not ask for confirmation")
+                        .takes_value(false),
+                )
+                .args(filter::args())
+                .args(format::args())
+                .arg_required_else_help(true),
+        )
+        .subcommand(
+            Command::new("remote-protocol")
+                .about("Convert the remotes of repositories between HTTPS and SSH, the location of repositories is not changed")
+                .arg(
+                    Arg::new("PROTOCOL")
+                        .help("The protocol of remotes")
+                        .required(true)
+                        .possible_values(protocol::Protocol::NAMES),
+                )
+                .arg(
+                    Arg::new("host-rule")
+                        .long("host-rule")
+                        .help("How to reach the host with SSH. eg. --host-rule github.com=git@ssh.github.com:443")
+                        .takes_value(true)
+                        .multiple_occurrences(true)
+                        .validator(|v| protocol::HostRule::parse(v).map(|_| ())),
+                )
+                .arg(
+                    Arg::new("dry-run")
+                        .long("dry-run")
+                        .help("Print what would be changed without changing")
+                        .takes_value(false),
+                )
+                .args(filter::args())
+                .args(format::args()),
+        )
+        .subcommand(
+            Command::new("audit-remotes")
+                .about("Find the credentials embedded in the remote URLs of repositories")
+                .arg(
+                    Arg::new("scrub")
+                        .long("scrub")
+                        .help("Remove the credentials from the remote URLs")
+                        .takes_value(false),
+                )
+                .args(filter::args())
+                .args(format::args()),
+        )
+        .subcommand(
+            Command::new("dedupe")
+                .about("Find the clones of the same remote, merge the unpushed branches into the kept one and move the others to trash")
+                .arg(
+                    Arg::new("dry-run")
+                        .long("dry-run")
+                        .help("Print what would be done without changing")
+                        .takes_value(false),
+                )
+                .arg(
+                    Arg::new("yes")
+                        .short('y')
+                        .long("yes")
+                        .help("Keep the clone in place without asking")
+                        .takes_value(false),
+                )
+                .arg(
+                    Arg::new("force")
+                        .short('f')
+                        .long("force")
+                        .help("Remove the clones with uncommitted changes, untracked files or stashes")
+                        .takes_value(false),
+                )
+                .args(filter::args())
+                .args(format::args()),
+        )
+        .subcommand(
+            Command::new("doctor")
+                .about("Check git, the configure, the roots and the repositories, and print how to fix the problems")
+                .arg(
+                    Arg::new("fix")
+                        .long("fix")
+                        .help("Fix the problems which are safe to fix. eg. create the missing roots, remove the empty folders")
+                        .takes_value(false),
+                )
+                .args(format::args()),
+        )
+        .subcommand(
+            Command::new("info")
+                .about("Print the information of repository")
+                .arg(arg!(<REMOTE_OR_PATH> "The remote Git URL or folder path of repository"))
+                .args(format::args()),
+        )
         .subcommand(
             Command::new("open")
                 .about("Open repository with file explorer")
@@ -88,6 +708,7 @@ fn main() {
                 .about(
                     "The operation of configure, print the configure if sub-command not provide.",
                 )
+                .args(format::args())
                 .subcommand(
                     Command::new("add")
                         .about("Add configure for a field")
@@ -137,10 +758,18 @@ fn main() {
         drop(file);
     }
 
-    let mut rc = configure::new(&gpm_rc).expect("invalid configure file");
-
     let matches = app.clone().get_matches();
 
+    // the invalid configure is reported by doctor
+    let mut rc = match configure::new(&gpm_rc) {
+        Ok(rc) => rc,
+        Err(_) if matches!(matches.subcommand(), Some(("doctor", _))) => configure::Configure {
+            file_path: gpm_rc.to_string_lossy().to_string(),
+            root: vec![],
+        },
+        Err(e) => panic!("invalid configure file: {:?}", e),
+    };
+
     fn check_gpm_root(rc: &configure::Configure) {
         if rc.root.is_empty() {
             println!("Can not found root folder in the configure.\nTry running the following command to add a default folder:\n\n    gpm config add root $HOME/gpm\n\nOr set to a custom folder:\n\n    gpm config add root <folder>\n");
@@ -169,62 +798,204 @@ fn main() {
 
     match matches.subcommand() {
         Some(("clone", sub_matches)) => {
-            let url = sub_matches.value_of("REMOTE").expect("required");
+            let remotes = sub_matches
+                .values_of("REMOTE")
+                .map(|v| v.collect::<Vec<&str>>())
+                .unwrap_or_default();
 
             let clone_args = match sub_matches.values_of("OPTIONS") {
                 Some(s) => s.collect::<Vec<&str>>(),
                 _ => vec![],
             };
 
-            let gpm_root: &str = get_gpm_root(&rc);
+            // clone many repositories without prompt
+            if remotes.len() != 1
+                || remotes.contains(&"-")
+                || sub_matches.is_present("from-file")
+                || sub_matches.is_present("on-conflict")
+            {
+                let format = output_format(sub_matches);
 
-            let mut dest_dir = git::url_to_path(gpm_root, url).unwrap();
+                let mut urls: Vec<String> = vec![];
 
-            // if project exist
-            if dest_dir.exists() {
-                let options: Vec<&str> = vec!["Auto", "Override", "Rename", "Open", "Cancel"];
+                let read = |result: Result<Vec<String>, eyre::Report>, source: &str| {
+                    result.unwrap_or_else(|e| {
+                        eprintln!("can not read the URLs from {}: {}", source, e);
+                        process::exit(0x1);
+                    })
+                };
 
-                let ans: Result<&str, InquireError> =
-                    Select::new("The project exist, then you want: ", options).prompt();
+                for remote in &remotes {
+                    if *remote == "-" {
+                        urls.extend(read(clone::read_urls(io::stdin().lock()), "stdin"));
+                    } else {
+                        urls.push(remote.to_string());
+                    }
+                }
 
-                dest_dir = match ans {
-                    Ok("Auto") => util::find_available_path(dest_dir),
-                    Ok("Override") => {
-                        let ans = Confirm::new("Override means that the original project will be deleted, are you sure you want to continue?")
-                            .with_default(false)
-                            .with_help_message(
-                                "[DANGER]: The data cannot be restored.",
-                            )
-                            .prompt();
+                if let Some(file) = sub_matches.value_of("from-file") {
+                    let result = File::open(file)
+                        .map_err(eyre::Report::from)
+                        .and_then(|f| clone::read_urls(io::BufReader::new(f)));
 
-                        match ans {
-                            Ok(true) => fs::remove_dir_all(dest_dir.clone()).unwrap(),
-                            Ok(false) => process::exit(0x0),
-                            Err(_) => process::exit(0x0),
-                        };
+                    urls.extend(read(result, file));
+                }
 
-                        dest_dir
-                    }
-                    Ok("Rename") => {
-                        let mut new_dest_dir = dest_dir.clone();
+                let gpm_root: &str = get_gpm_root(&rc);
 
-                        while new_dest_dir.exists() {
-                            let dir_name = new_dest_dir
-                                .file_name()
-                                .unwrap()
-                                .to_str()
-                                .unwrap()
-                                .to_string();
+                let urls = clone::dedupe(gpm_root, urls);
 
-                            let help_msg =
-                                format!("The project name '{}' already exists.", &dir_name);
+                if urls.is_empty() {
+                    eprintln!("No repository to clone");
+                    process::exit(0x1);
+                }
 
-                            let input =
-                                Text::new("Enter the new name:").with_help_message(&help_msg);
+                let options = clone::Options {
+                    jobs: parallel::jobs_from_matches(sub_matches),
+                    host_jobs: sub_matches
+                        .value_of("host-jobs")
+                        .and_then(|v| v.parse::<usize>().ok())
+                        .unwrap_or(usize::MAX),
+                    conflict: clone::Conflict::parse(
+                        sub_matches.value_of("on-conflict").unwrap_or("skip"),
+                    )
+                    .unwrap(),
+                    args: clone_args.iter().map(|a| a.to_string()).collect(),
+                    // git can not prompt for the credentials of many repositories at the same time
+                    network: network::Options::from_matches(sub_matches, false).unwrap_or_else(
+                        |e| {
+                            eprintln!("{}", e);
+                            process::exit(0x1);
+                        },
+                    ),
+                    strip_credentials: sub_matches.is_present("strip-credentials"),
+                };
 
-                            new_dest_dir = match input.prompt() {
-                                Ok(name) => new_dest_dir.parent().unwrap().join(name),
-                                Err(_) => process::exit(0x0),
+                network::handle_interrupt();
+
+                let results = clone::run(gpm_root, &urls, &options);
+
+                if network::is_cancelled() {
+                    process::exit(130);
+                }
+
+                match format {
+                    Some(format) => clone::output(&urls, &results).print(&format),
+                    None => {
+                        for line in clone::report(&urls, &results) {
+                            println!("{}", line);
+                        }
+                    }
+                }
+
+                if results
+                    .iter()
+                    .any(|r| matches!(r, clone::CloneResult::Failed(_)))
+                {
+                    process::exit(0x1);
+                }
+
+                process::exit(0x0);
+            }
+
+            let url = remotes[0];
+            let strip_credentials = sub_matches.is_present("strip-credentials");
+
+            if !strip_credentials && remote::strip_credentials(url).is_some() {
+                eprintln!("{}", clone::CREDENTIALS_REFUSED);
+                process::exit(0x1);
+            }
+
+            // git may prompt for the credentials only if gpm is run in terminal
+            let network_options =
+                network::Options::from_matches(sub_matches, io::stdin().is_terminal())
+                    .unwrap_or_else(|e| {
+                        eprintln!("{}", e);
+                        process::exit(0x1);
+                    });
+
+            let gpm_root: &str = get_gpm_root(&rc);
+
+            let mut dest_dir = git::url_to_path(gpm_root, url).unwrap();
+            let mut override_existing = false;
+
+            let lock_destination = |dest: &Path| match staging::lock_destination(gpm_root, dest) {
+                Ok(Some(lock)) => lock,
+                Ok(None) => {
+                    eprintln!(
+                        "Another gpm is cloning into '{}', try again when it finished",
+                        dest.display()
+                    );
+                    process::exit(0x1);
+                }
+                Err(e) => {
+                    eprintln!("can not lock '{}': {}", dest.display(), e);
+                    process::exit(0x1);
+                }
+            };
+
+            // keep the lock until the clone finished
+            let mut locks = vec![lock_destination(&dest_dir)];
+            let locked_dir = dest_dir.clone();
+
+            match staging::clean(gpm_root) {
+                Ok(cleaned) => {
+                    for path in cleaned {
+                        eprintln!(
+                            "Cleaned the leftover of interrupted clone '{}'",
+                            path.display()
+                        );
+                    }
+                }
+                Err(e) => eprintln!("can not clean the staging folder: {}", e),
+            }
+
+            // if project exist
+            if dest_dir.exists() {
+                let options: Vec<&str> = vec!["Auto", "Override", "Rename", "Open", "Cancel"];
+
+                let ans: Result<&str, InquireError> =
+                    Select::new("The project exist, then you want: ", options).prompt();
+
+                dest_dir = match ans {
+                    Ok("Auto") => util::find_available_path(dest_dir),
+                    Ok("Override") => {
+                        let ans = Confirm::new("Override means that the original project will be moved to trash, are you sure you want to continue?")
+                            .with_default(false)
+                            .with_help_message(
+                                "The project can be restored by 'gpm trash restore'.",
+                            )
+                            .prompt();
+
+                        match ans {
+                            // the original project is moved to trash after the clone succeed
+                            Ok(true) => override_existing = true,
+                            Ok(false) => process::exit(0x0),
+                            Err(_) => process::exit(0x0),
+                        };
+
+                        dest_dir
+                    }
+                    Ok("Rename") => {
+                        let mut new_dest_dir = dest_dir.clone();
+
+                        while new_dest_dir.exists() {
+                            let dir_name = new_dest_dir
+                                .file_name()
+                                .unwrap()
+                                .to_str()
+                                .unwrap()
+                                .to_string();
+
+                            let help_msg =
+                                format!("The project name '{}' already exists.", &dir_name);
+
+                            let input =
+                                Text::new("Enter the new name:").with_help_message(&help_msg);
+
+                            new_dest_dir = match input.prompt() {
+                                Ok(name) => new_dest_dir.parent().unwrap().join(name),
+                                Err(_) => process::exit(0x0),
                             }
                         }
 
@@ -239,23 +1010,77 @@ fn main() {
                 }
             }
 
-            let rm_dir = dest_dir.clone();
+            // the destination is changed by 'Auto' or 'Rename'
+            if dest_dir != locked_dir {
+                locks.push(lock_destination(&dest_dir));
+            }
 
-            // remove temp dir when cancel the action
-            ctrlc::set_handler(move || {
-                if rm_dir.exists() && fs::remove_dir_all(rm_dir.as_path()).is_ok() {}
-            })
-            .unwrap_or_else(|e| println!("Error setting Ctrl-C handler: {}", e));
+            let name = dest_dir
+                .file_name()
+                .map(|n| n.to_string_lossy().to_string())
+                .unwrap_or_default();
 
-            match git::clone(url, &dest_dir, clone_args) {
-                Ok(()) => file_explorer::open(&dest_dir),
-                _ => {
-                    if dest_dir.exists() {
-                        fs::remove_dir_all(dest_dir).unwrap();
+            // clone into the staging folder of the same root, then rename it to the destination
+            let staging = match staging::Staging::new(gpm_root, &name) {
+                Ok(staging) => staging,
+                Err(e) => {
+                    drop(locks);
+
+                    eprintln!("can not create the staging folder: {}", e);
+                    process::exit(0x1);
+                }
+            };
+
+            // kill git when Ctrl-C, then the staging folder is removed when dropped
+            network::handle_interrupt();
+
+            if let Err(e) = git::clone(url, &staging.path, clone_args, &network_options) {
+                drop(staging);
+                drop(locks);
+
+                if network::is_cancelled() {
+                    process::exit(130);
+                }
+
+                eprintln!("{}", e);
+                process::exit(0x1);
+            }
+
+            if strip_credentials {
+                if let Err(e) = clone::scrub_origin(&staging.path, url) {
+                    // the clone with the credentials must not be kept
+                    drop(staging);
+                    drop(locks);
+
+                    eprintln!("can not remove the credentials: {}", e);
+                    process::exit(0x1);
+                }
+            }
+
+            if override_existing {
+                match trash::trash(gpm_root, &dest_dir) {
+                    Ok(entry) => println!("Moved the original project to trash '{}'", entry.id),
+                    Err(e) => {
+                        drop(staging);
+                        drop(locks);
+
+                        eprintln!("can not move the project to trash: {}", e);
                         process::exit(0x1);
                     }
                 }
             }
+
+            let result = staging.finish(&dest_dir);
+
+            drop(locks);
+
+            match result {
+                Ok(()) => file_explorer::open(&dest_dir),
+                Err(e) => {
+                    eprintln!("{}", e);
+                    process::exit(0x1);
+                }
+            }
         }
         Some(("config", sub_matches)) => match sub_matches.subcommand() {
             Some(("add", sub_matches)) => {
@@ -277,44 +1102,1400 @@ fn main() {
                 rc.reset().unwrap();
             }
             _ => {
-                println!("{}", rc);
+                match output_format(sub_matches) {
+                    Some(format) => {
+                        let mut output = Output::new("configure", &["field", "value"]);
+
+                        for root in &rc.root {
+                            output.push(vec![json!("root"), json!(root)]);
+                        }
+
+                        output.print(&format);
+                    }
+                    None => println!("{}", rc),
+                }
+
                 process::exit(0x0);
             }
         },
         Some(("list", sub_matches)) => {
             check_gpm_root(&rc);
             let is_output_as_json = sub_matches.is_present("json");
+            let format = output_format(sub_matches);
 
-            if is_output_as_json {
-                let mut repository_map: HashMap<String, Vec<String>> = HashMap::new();
+            let columns = sub_matches
+                .values_of("columns")
+                .unwrap_or_default()
+                .map(|c| Column::parse(c).unwrap())
+                .collect::<Vec<Column>>();
 
-                for gpm_root in rc.root {
-                    let root = Path::new(&gpm_root);
+            let filter = filter::Filter::from_matches(sub_matches).unwrap_or_else(|e| {
+                eprintln!("{}", e);
+                process::exit(0x1);
+            });
 
-                    let repositories = walker::walk_root(root)
-                        .unwrap()
-                        .into_iter()
-                        .map(|v| v.as_os_str().to_str().unwrap().to_string())
-                        .collect::<Vec<String>>();
+            let repositories = filter.apply(repository::scan(&rc.root));
 
-                    repository_map.insert(gpm_root.clone(), repositories);
-                }
+            let metadata = parallel::map(&repositories, parallel::default_jobs(), |r| {
+                r.metadata(&columns)
+            });
+
+            let entries = repositories.iter().zip(metadata.iter());
+
+            if let Some(format) = format {
+                repositories_output(&repositories, &metadata, &columns).print(&format);
+            } else if is_output_as_json {
+                let serialized = if columns.is_empty() {
+                    let mut repository_map: HashMap<&str, Vec<&str>> =
+                        rc.root.iter().map(|r| (r.as_str(), vec![])).collect();
+
+                    for (repo, _) in entries {
+                        repository_map
+                            .entry(&repo.root)
+                            .or_default()
+                            .push(repo.path_str());
+                    }
+
+                    serde_json::to_string(&repository_map).unwrap()
+                } else {
+                    let mut repository_map: HashMap<&str, Vec<ListEntry>> =
+                        rc.root.iter().map(|r| (r.as_str(), vec![])).collect();
+
+                    for (repo, metadata) in entries {
+                        repository_map
+                            .entry(&repo.root)
+                            .or_default()
+                            .push(ListEntry {
+                                path: repo.path_str(),
+                                metadata,
+                            });
+                    }
 
-                let serialized = serde_json::to_string(&repository_map).unwrap();
+                    serde_json::to_string(&repository_map).unwrap()
+                };
 
                 println!("{}", serialized)
             } else {
-                for gpm_root in rc.root {
-                    let root = Path::new(&gpm_root);
-                    if root.exists() {
-                        let repositories = walker::walk_root(root).unwrap();
-                        println!("{}", gpm_root);
-
-                        for v in repositories {
-                            println!("    {}", v.as_os_str().to_str().unwrap())
+                for gpm_root in rc.root.iter().filter(|r| Path::new(r).exists()) {
+                    println!("{}", gpm_root);
+
+                    for (repo, metadata) in entries.clone().filter(|(r, _)| &r.root == gpm_root) {
+                        let values = columns
+                            .iter()
+                            .map(|c| metadata.value(*c))
+                            .collect::<Vec<String>>();
+
+                        if values.is_empty() {
+                            println!("    {}", repo.path_str())
+                        } else {
+                            println!("    {}  {}", repo.path_str(), values.join("  "))
+                        }
+                    }
+                }
+            }
+        }
+        Some(("status", sub_matches)) => {
+            check_gpm_root(&rc);
+
+            let format = if sub_matches.is_present("json") {
+                Some(Format::Json)
+            } else {
+                output_format(sub_matches)
+            };
+
+            let filter = filter::Filter::from_matches(sub_matches).unwrap_or_else(|e| {
+                eprintln!("{}", e);
+                process::exit(0x1);
+            });
+
+            let repositories = filter.apply(repository::scan(&rc.root));
+
+            let statuses = parallel::map(&repositories, parallel::default_jobs(), |r| {
+                status::Status::check(&r.path)
+            });
+
+            let (attention, attention_statuses): (Vec<Repository>, Vec<status::Status>) =
+                repositories
+                    .iter()
+                    .cloned()
+                    .zip(statuses.iter().cloned())
+                    .filter(|(_, s)| s.needs_attention())
+                    .unzip();
+
+            match format {
+                Some(format) => status::output(&attention, &attention_statuses).print(&format),
+                None => {
+                    for (repo, status) in attention.iter().zip(attention_statuses.iter()) {
+                        println!("{}  {}", repo.path_str(), status.reasons().join(", "));
+                    }
+
+                    println!("{}", status::summary(repositories.len(), &statuses));
+                }
+            }
+
+            if !attention.is_empty() {
+                process::exit(0x1);
+            }
+        }
+        Some(("exec", sub_matches)) => {
+            check_gpm_root(&rc);
+
+            let format = output_format(sub_matches).unwrap_or(Format::Table);
+
+            let filter = filter::Filter::from_matches(sub_matches).unwrap_or_else(|e| {
+                eprintln!("{}", e);
+                process::exit(0x1);
+            });
+
+            let options = exec::Options {
+                command: sub_matches
+                    .values_of("COMMAND")
+                    .expect("required")
+                    .map(|s| s.to_string())
+                    .collect(),
+                jobs: parallel::jobs_from_matches(sub_matches),
+                mode: exec::OutputMode::parse(sub_matches.value_of("output").expect("default"))
+                    .unwrap(),
+                fail_fast: sub_matches.is_present("fail-fast"),
+            };
+
+            let repositories = filter.apply(repository::scan(&rc.root));
+
+            let results = exec::run(&repositories, &options);
+
+            println!();
+            exec::output(&repositories, &results).print(&format);
+
+            if results.iter().any(|r| !r.is_success()) {
+                process::exit(0x1);
+            }
+        }
+        Some((action @ ("fetch" | "pull"), sub_matches)) => {
+            check_gpm_root(&rc);
+
+            let format = output_format(sub_matches);
+
+            let filter = filter::Filter::from_matches(sub_matches).unwrap_or_else(|e| {
+                eprintln!("{}", e);
+                process::exit(0x1);
+            });
+
+            let options = sync::Options {
+                action: if action == "fetch" {
+                    sync::Action::Fetch
+                } else {
+                    sync::Action::Pull
+                },
+                jobs: parallel::jobs_from_matches(sub_matches),
+                host_jobs: sub_matches
+                    .value_of("host-jobs")
+                    .and_then(|v| v.parse::<usize>().ok())
+                    .unwrap_or(usize::MAX),
+                network: network::Options::from_matches(sub_matches, false).unwrap_or_else(|e| {
+                    eprintln!("{}", e);
+                    process::exit(0x1);
+                }),
+            };
+
+            let repositories = filter.apply(repository::scan(&rc.root));
+
+            network::handle_interrupt();
+
+            let results = sync::run(&repositories, &options);
+
+            if network::is_cancelled() {
+                process::exit(130);
+            }
+
+            match format {
+                Some(format) => sync::output(&repositories, &results).print(&format),
+                None => println!("{}", sync::summary(&results)),
+            }
+
+            if results
+                .iter()
+                .any(|r| matches!(r, sync::SyncResult::Failed(_)))
+            {
+                process::exit(0x1);
+            }
+        }
+        Some(("news", sub_matches)) => {
+            check_gpm_root(&rc);
+
+            let format = output_format(sub_matches);
+
+            let since = sub_matches.value_of("since").map(|v| {
+                util::parse_duration(v)
+                    .unwrap_or_else(|e| {
+                        eprintln!("{}", e);
+                        process::exit(0x1);
+                    })
+                    .as_secs()
+            });
+
+            let filter = filter::Filter::from_matches(sub_matches).unwrap_or_else(|e| {
+                eprintln!("{}", e);
+                process::exit(0x1);
+            });
+
+            let repositories = filter.apply(repository::scan(&rc.root));
+
+            let news = parallel::map(&repositories, parallel::default_jobs(), |r| {
+                news::collect(&r.path, since).unwrap_or_else(|e| {
+                    eprintln!("can not read the news of '{}': {}", r.path_str(), e);
+                    vec![]
+                })
+            });
+
+            let news = repositories
+                .into_iter()
+                .zip(news)
+                .filter(|(_, n)| !n.is_empty())
+                .collect::<Vec<_>>();
+
+            match format {
+                Some(format) => news::output(&news).print(&format),
+                None => {
+                    for (repo, items) in &news {
+                        println!("{}", repo.path_str());
+
+                        for item in items {
+                            for line in item.lines() {
+                                println!("    {}", line);
+                            }
+                        }
+                    }
+
+                    if news.is_empty() {
+                        println!("No news since the last fetch, run 'gpm fetch' to check updates");
+                    }
+                }
+            }
+        }
+        Some(("log", sub_matches)) => {
+            check_gpm_root(&rc);
+
+            let format = output_format(sub_matches);
+
+            let filter = filter::Filter::from_matches(sub_matches).unwrap_or_else(|e| {
+                eprintln!("{}", e);
+                process::exit(0x1);
+            });
+
+            let options = log::Options::from_matches(sub_matches);
+
+            let chronological = sub_matches.is_present("chronological");
+
+            let repositories = filter.apply(repository::scan(&rc.root));
+
+            let commits = parallel::map(&repositories, parallel::default_jobs(), |r| {
+                log::collect(&r.path, &options).unwrap_or_else(|e| {
+                    eprintln!("can not read the log of '{}': {}", r.path_str(), e);
+                    vec![]
+                })
+            });
+
+            let commits = repositories
+                .into_iter()
+                .zip(commits)
+                .filter(|(_, c)| !c.is_empty())
+                .collect::<Vec<_>>();
+
+            match format {
+                Some(format) => log::output(&commits, chronological).print(&format),
+                None if sub_matches.is_present("markdown") => {
+                    println!("{}", log::markdown(&commits, chronological))
+                }
+                None if commits.is_empty() => println!("No commits in the date range"),
+                None => println!("{}", log::text(&commits, chronological)),
+            }
+        }
+        Some(("stats", sub_matches)) => {
+            check_gpm_root(&rc);
+
+            let format = output_format(sub_matches).unwrap_or(Format::Table);
+
+            let filter = filter::Filter::from_matches(sub_matches).unwrap_or_else(|e| {
+                eprintln!("{}", e);
+                process::exit(0x1);
+            });
+
+            let options = log::Options::from_matches(sub_matches);
+
+            let repositories = filter.apply(repository::scan(&rc.root));
+
+            let stats = parallel::map(&repositories, parallel::default_jobs(), |r| {
+                stats::collect(&r.path, &options).unwrap_or_else(|e| {
+                    eprintln!("can not read the log of '{}': {}", r.path_str(), e);
+                    vec![]
+                })
+            });
+
+            let stats = repositories
+                .into_iter()
+                .zip(stats)
+                .filter(|(_, s)| !s.is_empty())
+                .collect::<Vec<_>>();
+
+            match sub_matches.value_of("by").expect("default") {
+                "repo" => stats::repository_output(&stats).print(&format),
+                _ => stats::author_output(&stats).print(&format),
+            }
+        }
+        Some(("grep", sub_matches)) => {
+            check_gpm_root(&rc);
+
+            let format = output_format(sub_matches);
+
+            let filter = filter::Filter::from_matches(sub_matches).unwrap_or_else(|e| {
+                eprintln!("{}", e);
+                process::exit(0x1);
+            });
+
+            let options = grep::Options::new(
+                sub_matches.value_of("PATTERN").expect("required"),
+                sub_matches.is_present("fixed-strings"),
+                sub_matches.is_present("ignore-case"),
+                &sub_matches
+                    .values_of("glob")
+                    .map(|v| v.collect::<Vec<&str>>())
+                    .unwrap_or_default(),
+                sub_matches
+                    .value_of("context")
+                    .expect("default")
+                    .parse()
+                    .unwrap(),
+            )
+            .unwrap_or_else(|e| {
+                eprintln!("{}", e);
+                process::exit(0x1);
+            });
+
+            let repositories = filter.apply(repository::scan(&rc.root));
+
+            let matches = parallel::map(
+                &repositories,
+                parallel::jobs_from_matches(sub_matches),
+                |r| {
+                    grep::search(&r.path, &options).unwrap_or_else(|e| {
+                        eprintln!("can not search '{}': {}", r.path_str(), e);
+                        vec![]
+                    })
+                },
+            );
+
+            let matches = repositories
+                .into_iter()
+                .zip(matches)
+                .filter(|(_, m)| !m.is_empty())
+                .collect::<Vec<_>>();
+
+            match format {
+                Some(format) => grep::output(&matches).print(&format),
+                None => {
+                    for (repo, items) in &matches {
+                        println!("{}", repo.path_str());
+
+                        for item in items {
+                            for line in grep::lines(item) {
+                                println!("    {}", line);
+                            }
+                        }
+                    }
+                }
+            }
+
+            // same as grep, exit with non-zero code if nothing matched
+            if matches.is_empty() {
+                process::exit(0x1);
+            }
+        }
+        Some(("search-commits", sub_matches)) => {
+            check_gpm_root(&rc);
+
+            let format = output_format(sub_matches);
+
+            let filter = filter::Filter::from_matches(sub_matches).unwrap_or_else(|e| {
+                eprintln!("{}", e);
+                process::exit(0x1);
+            });
+
+            let options = log::Options::from_matches(sub_matches);
+
+            let search = log::Search {
+                pattern: sub_matches
+                    .value_of("PATTERN")
+                    .expect("required")
+                    .to_string(),
+                diff: sub_matches.is_present("diff"),
+                ignore_case: sub_matches.is_present("ignore-case"),
+            };
+
+            let chronological = sub_matches.is_present("chronological");
+
+            let repositories = filter.apply(repository::scan(&rc.root));
+
+            let commits = parallel::map(&repositories, parallel::default_jobs(), |r| {
+                log::search(&r.path, &options, &search).unwrap_or_else(|e| {
+                    eprintln!("can not search '{}': {}", r.path_str(), e);
+                    vec![]
+                })
+            });
+
+            let commits = repositories
+                .into_iter()
+                .zip(commits)
+                .filter(|(_, c)| !c.is_empty())
+                .collect::<Vec<_>>();
+
+            match format {
+                Some(format) => log::output(&commits, chronological).print(&format),
+                None => println!("{}", log::text(&commits, chronological)),
+            }
+
+            if commits.is_empty() {
+                process::exit(0x1);
+            }
+        }
+        Some(("rm", sub_matches)) => {
+            check_gpm_root(&rc);
+
+            let query = sub_matches.value_of("QUERY").expect("required");
+            let format = output_format(sub_matches);
+            let force = sub_matches.is_present("force");
+            let dry_run = sub_matches.is_present("dry-run");
+
+            let repositories = find_repositories(&rc.root, query).unwrap_or_else(|e| {
+                eprintln!("{}", e);
+                process::exit(0x1);
+            });
+
+            if repositories.is_empty() {
+                println!("Could not found the cloned repository '{}'", query);
+                process::exit(0x1);
+            }
+
+            let losses = parallel::map(&repositories, parallel::default_jobs(), |r| {
+                remove::Loss::check(&r.path)
+            });
+
+            let removable = |loss: &remove::Loss| force || loss.is_empty();
+
+            for (repo, loss) in repositories.iter().zip(losses.iter()) {
+                eprintln!(
+                    "{} {}",
+                    match (removable(loss), dry_run) {
+                        (false, _) => "refuse",
+                        (true, true) => "would remove",
+                        (true, false) => "remove",
+                    },
+                    repo.path_str()
+                );
+
+                for line in loss.lines() {
+                    eprintln!("    {}", line);
+                }
+            }
+
+            let count = losses.iter().filter(|l| removable(l)).count();
+
+            let confirmed = dry_run
+                || count == 0
+                || sub_matches.is_present("yes")
+                || Confirm::new(&format!(
+                    "Remove {} {}?",
+                    count,
+                    if count == 1 {
+                        "repository"
+                    } else {
+                        "repositories"
+                    }
+                ))
+                .with_default(false)
+                .prompt()
+                .unwrap_or(false);
+
+            if !confirmed {
+                process::exit(0x0);
+            }
+
+            let results = repositories
+                .iter()
+                .zip(losses.iter())
+                .map(|(repo, loss)| match (removable(loss), dry_run) {
+                    (false, _) => "refused".to_string(),
+                    (true, true) => "would-remove".to_string(),
+                    (true, false) => match remove::remove(repo) {
+                        Ok(_) => "removed".to_string(),
+                        Err(e) => format!("failed: {}", e),
+                    },
+                })
+                .collect::<Vec<String>>();
+
+            match format {
+                Some(format) => remove::output(
+                    &repositories,
+                    &losses,
+                    &results.iter().map(|r| r.as_str()).collect::<Vec<&str>>(),
+                )
+                .print(&format),
+                None => {
+                    for (repo, result) in repositories.iter().zip(results.iter()) {
+                        println!("{:12} {}", result, repo.path_str());
+                    }
+                }
+            }
+
+            if results.iter().any(|r| r == "removed") {
+                eprintln!("The removed repositories are moved to trash, run 'gpm trash restore' to restore");
+            }
+
+            if results
+                .iter()
+                .any(|r| r != "removed" && r != "would-remove")
+            {
+                if !force {
+                    eprintln!("Use --force to remove the repositories with work would be lost");
+                }
+
+                process::exit(0x1);
+            }
+        }
+        Some(("trash", sub_matches)) => {
+            check_gpm_root(&rc);
+
+            let entries = trash::list(&rc.root);
+
+            match sub_matches.subcommand() {
+                Some(("restore", sub_matches)) => {
+                    let id = sub_matches.value_of("ID_OR_PATH").expect("required");
+
+                    let entry = entries
+                        .iter()
+                        .find(|e| {
+                            e.id == id || e.meta.path == id || e.meta.remote.as_deref() == Some(id)
+                        })
+                        .unwrap_or_else(|| {
+                            eprintln!("Could not found '{}' in trash", id);
+                            process::exit(0x1);
+                        });
+
+                    match trash::restore(entry) {
+                        Ok(path) => println!("Restored '{}'", path.display()),
+                        Err(e) => {
+                            eprintln!("{}", e);
+                            process::exit(0x1);
+                        }
+                    }
+                }
+                Some(("purge", sub_matches)) => {
+                    let older_than = sub_matches.value_of("older-than").map(|v| {
+                        util::parse_duration(v)
+                            .unwrap_or_else(|e| {
+                                eprintln!("{}", e);
+                                process::exit(0x1);
+                            })
+                            .as_secs()
+                    });
+
+                    let now = std::time::SystemTime::now()
+                        .duration_since(std::time::UNIX_EPOCH)
+                        .unwrap()
+                        .as_secs();
+
+                    let entries = entries
+                        .into_iter()
+                        .filter(|e| match older_than {
+                            Some(seconds) => e.meta.time < now.saturating_sub(seconds),
+                            None => true,
+                        })
+                        .collect::<Vec<trash::Entry>>();
+
+                    if entries.is_empty() {
+                        println!("Nothing to purge");
+                        return;
+                    }
+
+                    let confirmed = sub_matches.is_present("yes")
+                        || Confirm::new(&format!(
+                            "Remove {} {} in trash permanently?",
+                            entries.len(),
+                            if entries.len() == 1 {
+                                "folder"
+                            } else {
+                                "folders"
+                            }
+                        ))
+                        .with_default(false)
+                        .with_help_message("[DANGER]: The data cannot be restored.")
+                        .prompt()
+                        .unwrap_or(false);
+
+                    if !confirmed {
+                        process::exit(0x0);
+                    }
+
+                    for entry in &entries {
+                        match trash::purge(entry) {
+                            Ok(()) => println!("Purged {} {}", entry.id, entry.meta.path),
+                            Err(e) => eprintln!("can not purge '{}': {}", entry.id, e),
+                        }
+                    }
+                }
+                Some(("list", sub_matches)) => print_trash(&entries, output_format(sub_matches)),
+                _ => print_trash(&entries, output_format(sub_matches)),
+            }
+        }
+        Some(("export", sub_matches)) => {
+            check_gpm_root(&rc);
+
+            let filter = filter::Filter::from_matches(sub_matches).unwrap_or_else(|e| {
+                eprintln!("{}", e);
+                process::exit(0x1);
+            });
+
+            let file = sub_matches.value_of("FILE");
+
+            let manifest_format = match file {
+                _ if sub_matches.is_present("json") => manifest::ManifestFormat::Json,
+                Some(file) => manifest::ManifestFormat::from_path(Path::new(file)),
+                None => manifest::ManifestFormat::Toml,
+            };
+
+            let pin = sub_matches.is_present("pin");
+
+            let repositories = filter.apply(repository::scan(&rc.root));
+
+            let entries = parallel::map(&repositories, parallel::default_jobs(), |r| {
+                manifest::entry(r, pin)
+            });
+
+            let mut manifest = manifest::Manifest {
+                version: manifest::VERSION,
+                repositories: vec![],
+            };
+
+            for (repo, entry) in repositories.iter().zip(entries) {
+                match entry {
+                    Ok(Some(entry)) => manifest.repositories.push(entry),
+                    Ok(None) => eprintln!("Skipped '{}', it has no origin", repo.path_str()),
+                    Err(e) => eprintln!("can not export '{}': {}", repo.path_str(), e),
+                }
+            }
+
+            let content = manifest.to_string(manifest_format).unwrap_or_else(|e| {
+                eprintln!("can not serialize the manifest: {}", e);
+                process::exit(0x1);
+            });
+
+            match file {
+                Some(file) => {
+                    if let Err(e) = lock::write_atomic(Path::new(file), &content) {
+                        eprintln!("can not write to '{}': {}", file, e);
+                        process::exit(0x1);
+                    }
+
+                    eprintln!(
+                        "Exported {} {} to '{}'",
+                        manifest.repositories.len(),
+                        if manifest.repositories.len() == 1 {
+                            "repository"
+                        } else {
+                            "repositories"
+                        },
+                        file
+                    );
+                }
+                None => print!("{}", content),
+            }
+        }
+        Some(("restore", sub_matches)) => {
+            let file = sub_matches.value_of("MANIFEST").expect("required");
+            let format = output_format(sub_matches);
+
+            let manifest = fs::read_to_string(file)
+                .map_err(eyre::Report::from)
+                .and_then(|content| {
+                    manifest::Manifest::parse(
+                        &content,
+                        manifest::ManifestFormat::from_path(Path::new(file)),
+                    )
+                })
+                .unwrap_or_else(|e| {
+                    eprintln!("can not read the manifest '{}': {}", file, e);
+                    process::exit(0x1);
+                });
+
+            check_gpm_root(&rc);
+
+            let gpm_root: &str = get_gpm_root(&rc);
+
+            let options = clone::Options {
+                jobs: parallel::jobs_from_matches(sub_matches),
+                host_jobs: usize::MAX,
+                conflict: clone::Conflict::Skip,
+                args: vec![],
+                network: network::Options::from_matches(sub_matches, false).unwrap_or_else(|e| {
+                    eprintln!("{}", e);
+                    process::exit(0x1);
+                }),
+                strip_credentials: false,
+            };
+
+            let repositories = repository::scan(&rc.root);
+
+            network::handle_interrupt();
+
+            let results = manifest::restore(
+                &manifest,
+                &rc.root,
+                &repositories,
+                gpm_root,
+                &options,
+                sub_matches.is_present("dry-run"),
+            );
+
+            if network::is_cancelled() {
+                process::exit(130);
+            }
+
+            match format {
+                Some(format) => manifest::output(&results).print(&format),
+                None => {
+                    for line in manifest::report(&results) {
+                        println!("{}", line);
+                    }
+                }
+            }
+
+            if results
+                .iter()
+                .any(|(_, r)| matches!(r, manifest::RestoreResult::Failed(_)))
+            {
+                process::exit(0x1);
+            }
+        }
+        Some(("import", sub_matches)) => {
+            check_gpm_root(&rc);
+
+            let format = output_format(sub_matches);
+
+            let options = import::Options {
+                mode: import::Mode::parse(sub_matches.value_of("mode").expect("default")).unwrap(),
+                conflict: clone::Conflict::parse(
+                    sub_matches.value_of("on-conflict").expect("default"),
+                )
+                .unwrap(),
+                dry_run: sub_matches.is_present("dry-run"),
+            };
+
+            let mut sources: Vec<PathBuf> = vec![];
+
+            for dir in sub_matches.values_of("DIR").expect("required") {
+                // the link points to the absolute path
+                let found = fs::canonicalize(dir).and_then(|dir| import::discover(&dir));
+
+                match found {
+                    Ok(found) => sources.extend(found),
+                    Err(e) => {
+                        eprintln!("can not find the repositories in '{}': {}", dir, e);
+                        process::exit(0x1);
+                    }
+                }
+            }
+
+            if sources.is_empty() {
+                println!("No repository found");
+                return;
+            }
+
+            let gpm_root: &str = get_gpm_root(&rc);
+
+            // one by one, the repositories of the same origin are imported in order
+            let results = sources
+                .iter()
+                .map(|source| import::import(gpm_root, source, &options))
+                .collect::<Vec<import::ImportResult>>();
+
+            match format {
+                Some(format) => import::output(&sources, &results).print(&format),
+                None => {
+                    for line in import::report(&sources, &results) {
+                        println!("{}", line);
+                    }
+                }
+            }
+
+            if results
+                .iter()
+                .any(|r| matches!(r, import::ImportResult::Failed(_)))
+            {
+                process::exit(0x1);
+            }
+        }
+        Some(("relocate", sub_matches)) => {
+            check_gpm_root(&rc);
+
+            let format = output_format(sub_matches);
+            let dry_run = sub_matches.is_present("dry-run");
+
+            let filter = filter::Filter::from_matches(sub_matches).unwrap_or_else(|e| {
+                eprintln!("{}", e);
+                process::exit(0x1);
+            });
+
+            let repositories = filter.apply(repository::scan(&rc.root));
+
+            let checks = parallel::map(&repositories, parallel::default_jobs(), relocate::check);
+
+            let mut mismatched: Vec<Repository> = vec![];
+            let mut mismatches: Vec<relocate::Mismatch> = vec![];
+
+            for (repo, check) in repositories.into_iter().zip(checks) {
+                match check {
+                    Ok(Some(mismatch)) => {
+                        mismatched.push(repo);
+                        mismatches.push(mismatch);
+                    }
+                    Ok(None) => {}
+                    Err(e) => eprintln!("can not check '{}': {}", repo.path_str(), e),
+                }
+            }
+
+            if mismatches.is_empty() {
+                println!("All repositories match the origin");
+                return;
+            }
+
+            for (repo, mismatch) in mismatched.iter().zip(mismatches.iter()) {
+                eprintln!("{} -> {}", repo.path_str(), mismatch.dest.display());
+            }
+
+            let confirmed = dry_run
+                || sub_matches.is_present("yes")
+                || Confirm::new(&format!(
+                    "Move {} {}?",
+                    mismatches.len(),
+                    if mismatches.len() == 1 {
+                        "repository"
+                    } else {
+                        "repositories"
+                    }
+                ))
+                .with_default(false)
+                .prompt()
+                .unwrap_or(false);
+
+            if !confirmed {
+                process::exit(0x0);
+            }
+
+            let results = mismatched
+                .iter()
+                .zip(mismatches.iter())
+                .map(|(repo, mismatch)| {
+                    if dry_run {
+                        return "would-move".to_string();
+                    }
+
+                    match relocate::move_to(repo, &mismatch.dest) {
+                        Ok(()) => "moved".to_string(),
+                        Err(e) => format!("failed: {}", e),
+                    }
+                })
+                .collect::<Vec<String>>();
+
+            match format {
+                Some(format) => relocate::output(
+                    &mismatched,
+                    &mismatches,
+                    &results.iter().map(|r| r.as_str()).collect::<Vec<&str>>(),
+                )
+                .print(&format),
+                None => {
+                    for (repo, result) in mismatched.iter().zip(results.iter()) {
+                        println!("{:12} {}", result, repo.path_str());
+                    }
+                }
+            }
+
+            if results.iter().any(|r| r.starts_with("failed")) {
+                process::exit(0x1);
+            }
+        }
+        Some(("migrate-host", sub_matches)) => {
+            check_gpm_root(&rc);
+
+            let format = output_format(sub_matches);
+            let dry_run = sub_matches.is_present("dry-run");
+            let old_host = sub_matches.value_of("OLD").expect("required");
+            let new_host = sub_matches.value_of("NEW").expect("required");
+
+            let rules = sub_matches
+                .values_of("map-owner")
+                .map(|v| v.map(|r| migrate::Rule::parse(r).unwrap()).collect())
+                .unwrap_or_else(Vec::new);
+
+            let filter = filter::Filter::from_matches(sub_matches).unwrap_or_else(|e| {
+                eprintln!("{}", e);
+                process::exit(0x1);
+            });
+
+            let repositories = filter.apply(repository::scan(&rc.root));
+
+            let plans = parallel::map(&repositories, parallel::default_jobs(), |r| {
+                migrate::plan(r, old_host, new_host, &rules)
+            });
+
+            let mut affected: Vec<Repository> = vec![];
+            let mut affected_plans: Vec<migrate::Plan> = vec![];
+
+            for (repo, plan) in repositories.into_iter().zip(plans) {
+                match plan {
+                    Ok(Some(plan)) => {
+                        affected.push(repo);
+                        affected_plans.push(plan);
+                    }
+                    Ok(None) => {}
+                    Err(e) => eprintln!("can not plan '{}': {}", repo.path_str(), e),
+                }
+            }
+
+            if affected.is_empty() {
+                println!("No repository has the remote of '{}'", old_host);
+                return;
+            }
+
+            for (repo, plan) in affected.iter().zip(affected_plans.iter()) {
+                eprintln!("{} -> {}", repo.path_str(), plan.dest.display());
+
+                for (key, old, new) in &plan.urls {
+                    eprintln!("    {}: {} -> {}", remote::remote_name(key), old, new);
+                }
+            }
+
+            let confirmed = dry_run
+                || sub_matches.is_present("yes")
+                || Confirm::new(&format!(
+                    "Migrate {} {}?",
+                    affected.len(),
+                    if affected.len() == 1 {
+                        "repository"
+                    } else {
+                        "repositories"
+                    }
+                ))
+                .with_default(false)
+                .prompt()
+                .unwrap_or(false);
+
+            if !confirmed {
+                process::exit(0x0);
+            }
+
+            let results = affected
+                .iter()
+                .zip(affected_plans.iter())
+                .map(|(repo, plan)| {
+                    if dry_run {
+                        return "would-migrate".to_string();
+                    }
+
+                    match migrate::apply(repo, plan) {
+                        Ok(()) => "migrated".to_string(),
+                        Err(e) => format!("failed: {}", e),
+                    }
+                })
+                .collect::<Vec<String>>();
+
+            match format {
+                Some(format) => migrate::output(
+                    &affected,
+                    &affected_plans,
+                    &results.iter().map(|r| r.as_str()).collect::<Vec<&str>>(),
+                )
+                .print(&format),
+                None => {
+                    for (repo, result) in affected.iter().zip(results.iter()) {
+                        println!("{:14} {}", result, repo.path_str());
+                    }
+                }
+            }
+
+            if results.iter().any(|r| r.starts_with("failed")) {
+                process::exit(0x1);
+            }
+        }
+        Some(("remote-protocol", sub_matches)) => {
+            check_gpm_root(&rc);
+
+            let format = output_format(sub_matches);
+            let dry_run = sub_matches.is_present("dry-run");
+            let protocol =
+                protocol::Protocol::parse(sub_matches.value_of("PROTOCOL").expect("required"))
+                    .unwrap();
+
+            let rules = sub_matches
+                .values_of("host-rule")
+                .map(|v| v.map(|r| protocol::HostRule::parse(r).unwrap()).collect())
+                .unwrap_or_else(Vec::new);
+
+            let filter = filter::Filter::from_matches(sub_matches).unwrap_or_else(|e| {
+                eprintln!("{}", e);
+                process::exit(0x1);
+            });
+
+            let repositories = filter.apply(repository::scan(&rc.root));
+
+            let conversions =
+                parallel::map(
+                    &repositories,
+                    parallel::default_jobs(),
+                    |r| match protocol::plan(r, protocol, &rules) {
+                        Ok(conversion) if dry_run => conversion,
+                        Ok(conversion) => protocol::apply(r, conversion),
+                        Err(e) => protocol::Conversion {
+                            urls: vec![],
+                            errors: vec![(String::new(), String::new(), e.to_string())],
+                        },
+                    },
+                );
+
+            match format {
+                Some(format) => protocol::output(&repositories, &conversions).print(&format),
+                None => {
+                    for line in protocol::report(&repositories, &conversions) {
+                        println!("{}", line);
+                    }
+                }
+            }
+
+            if conversions.iter().any(|c| !c.errors.is_empty()) {
+                process::exit(0x1);
+            }
+        }
+        Some(("audit-remotes", sub_matches)) => {
+            check_gpm_root(&rc);
+
+            let format = output_format(sub_matches);
+            let scrub = sub_matches.is_present("scrub");
+
+            let filter = filter::Filter::from_matches(sub_matches).unwrap_or_else(|e| {
+                eprintln!("{}", e);
+                process::exit(0x1);
+            });
+
+            let repositories = filter.apply(repository::scan(&rc.root));
+
+            let checks = parallel::map(&repositories, parallel::default_jobs(), audit::check);
+
+            let mut affected: Vec<Repository> = vec![];
+            let mut findings: Vec<Vec<audit::Finding>> = vec![];
+
+            for (repo, check) in repositories.into_iter().zip(checks) {
+                match check {
+                    Ok(found) if found.is_empty() => {}
+                    Ok(found) => {
+                        affected.push(repo);
+                        findings.push(found);
+                    }
+                    Err(e) => eprintln!("can not check '{}': {}", repo.path_str(), e),
+                }
+            }
+
+            let results = affected
+                .iter()
+                .zip(findings.iter())
+                .map(|(repo, found)| {
+                    if !scrub {
+                        return "found".to_string();
+                    }
+
+                    match audit::scrub(repo, found) {
+                        Ok(()) => "scrubbed".to_string(),
+                        Err(e) => format!("failed: {}", e),
+                    }
+                })
+                .collect::<Vec<String>>();
+
+            match format {
+                Some(format) => audit::output(
+                    &affected,
+                    &findings,
+                    &results.iter().map(|r| r.as_str()).collect::<Vec<&str>>(),
+                )
+                .print(&format),
+                None => {
+                    for ((repo, found), result) in
+                        affected.iter().zip(findings.iter()).zip(results.iter())
+                    {
+                        println!("{:10} {}", result, repo.path_str());
+
+                        for finding in found {
+                            println!(
+                                "    {}: {}",
+                                remote::remote_name(&finding.key),
+                                finding.masked()
+                            );
+                        }
+                    }
+
+                    if affected.is_empty() {
+                        println!("No credentials found in the remote URLs");
+                    } else if !scrub {
+                        println!(
+                            "{} repositories with credentials, use --scrub to remove them",
+                            affected.len()
+                        );
+                    }
+                }
+            }
+
+            // the credentials are left if not scrubbed
+            if results.iter().any(|r| r != "scrubbed") {
+                process::exit(0x1);
+            }
+        }
+        Some(("dedupe", sub_matches)) => {
+            check_gpm_root(&rc);
+
+            let format = output_format(sub_matches);
+            let dry_run = sub_matches.is_present("dry-run");
+            let force = sub_matches.is_present("force");
+
+            let filter = filter::Filter::from_matches(sub_matches).unwrap_or_else(|e| {
+                eprintln!("{}", e);
+                process::exit(0x1);
+            });
+
+            let mut groups = dedupe::find(filter.apply(repository::scan(&rc.root)));
+
+            if groups.is_empty() {
+                println!("No duplicated clones");
+                return;
+            }
+
+            for group in &groups {
+                eprintln!("{}", group.remote);
+
+                for (i, member) in group.members.iter().enumerate() {
+                    eprintln!(
+                        "  {} {}  {}",
+                        if i == group.keep { "*" } else { " " },
+                        member.repo.path_str(),
+                        member.summary()
+                    );
+                }
+            }
+
+            // choose the clone to keep, the group is skipped if None
+            let keeps = groups
+                .iter()
+                .map(|group| {
+                    if dry_run || sub_matches.is_present("yes") {
+                        return Some(group.keep);
+                    }
+
+                    let mut options = group
+                        .members
+                        .iter()
+                        .map(|m| m.repo.path_str())
+                        .collect::<Vec<&str>>();
+
+                    options.push("Skip");
+
+                    match Select::new(&format!("Keep which clone of '{}'?", group.remote), options)
+                        .with_starting_cursor(group.keep)
+                        .raw_prompt()
+                    {
+                        Ok(answer) if answer.index < group.members.len() => Some(answer.index),
+                        Ok(_) => None,
+                        Err(_) => process::exit(0x0),
+                    }
+                })
+                .collect::<Vec<Option<usize>>>();
+
+            let resolutions = groups
+                .iter_mut()
+                .zip(keeps)
+                .map(|(group, keep)| match keep {
+                    Some(keep) => {
+                        group.keep = keep;
+
+                        let keeper = &group.members[keep];
+
+                        group
+                            .members
+                            .iter()
+                            .enumerate()
+                            .map(|(i, member)| {
+                                (i != keep).then(|| dedupe::resolve(keeper, member, force, dry_run))
+                            })
+                            .collect::<Vec<Option<dedupe::Resolution>>>()
+                    }
+                    None => group
+                        .members
+                        .iter()
+                        .map(|_| {
+                            Some(dedupe::Resolution {
+                                result: "skipped".to_string(),
+                                merged: vec![],
+                            })
+                        })
+                        .collect(),
+                })
+                .collect::<Vec<Vec<Option<dedupe::Resolution>>>>();
+
+            match format {
+                Some(format) => dedupe::output(&groups, &resolutions).print(&format),
+                None => {
+                    for (group, resolutions) in groups.iter().zip(resolutions.iter()) {
+                        for (member, resolution) in group.members.iter().zip(resolutions) {
+                            let result = resolution.as_ref().map(|r| r.result.as_str());
+
+                            println!("{:14} {}", result.unwrap_or("kept"), member.repo.path_str());
+
+                            for merged in resolution.iter().flat_map(|r| r.merged.iter()) {
+                                println!("    merged: {}", merged);
+                            }
+                        }
+                    }
+                }
+            }
+
+            let results = resolutions
+                .iter()
+                .flatten()
+                .flatten()
+                .map(|r| r.result.as_str())
+                .collect::<Vec<&str>>();
+
+            if results.contains(&"removed") {
+                eprintln!(
+                    "The removed clones are moved to trash, run 'gpm trash restore' to restore"
+                );
+            }
+
+            if results.contains(&"refused") {
+                eprintln!("Use --force to remove the clones with uncommitted changes, untracked files or stashes");
+            }
+
+            if results
+                .iter()
+                .any(|r| *r == "refused" || r.starts_with("failed"))
+            {
+                process::exit(0x1);
+            }
+        }
+        Some(("doctor", sub_matches)) => {
+            let format = output_format(sub_matches);
+            let fix = sub_matches.is_present("fix");
+
+            let git = doctor::check_git();
+            let git_found = git.level != doctor::Level::Error;
+
+            let mut findings = vec![git];
+
+            findings.extend(doctor::check_config(&gpm_rc));
+            findings.extend(doctor::check_roots(&rc.root));
+            findings.extend(doctor::check_openers());
+
+            // the missing roots are reported above
+            let roots = rc
+                .root
+                .iter()
+                .filter(|r| Path::new(r).is_dir())
+                .cloned()
+                .collect::<Vec<String>>();
+
+            if git_found {
+                findings.extend(doctor::check_layout(&roots, &repository::scan(&roots)));
+            }
+
+            let results = findings
+                .iter()
+                .map(|finding| match &finding.fix {
+                    Some(f) if fix => Some(match f.apply() {
+                        Ok(()) => "fixed".to_string(),
+                        Err(e) => format!("fix failed: {}", e),
+                    }),
+                    _ => None,
+                })
+                .collect::<Vec<Option<String>>>();
+
+            match format {
+                Some(format) => doctor::output(&findings, &results).print(&format),
+                None => {
+                    for line in doctor::report(&findings, &results) {
+                        println!("{}", line);
+                    }
+                }
+            }
+
+            let failed = findings.iter().zip(results.iter()).any(|(f, r)| match r {
+                Some(r) => r != "fixed",
+                None => f.level == doctor::Level::Error,
+            });
+
+            if failed {
+                process::exit(0x1);
+            }
+        }
+        Some(("info", sub_matches)) => {
+            let url = sub_matches.value_of("REMOTE_OR_PATH").expect("required");
+            let format = output_format(sub_matches);
+
+            let path = Path::new(url);
+
+            let repositories = if path.is_absolute() {
+                let root = rc
+                    .root
+                    .iter()
+                    .find(|r| path.starts_with(r))
+                    .cloned()
+                    .unwrap_or_default();
+
+                vec![Repository::new(&root, path.to_path_buf())]
+            } else {
+                rc.root
+                    .iter()
+                    .filter_map(|r| git::url_to_path(r, url).ok().map(|p| Repository::new(r, p)))
+                    .collect()
+            };
+
+            let repositories = repositories
+                .into_iter()
+                .filter(|r| r.path.is_dir())
+                .collect::<Vec<Repository>>();
+
+            if repositories.is_empty() {
+                println!("Could not found the cloned repository '{}'", url);
+                process::exit(0x1);
+            }
+
+            let metadata = parallel::map(&repositories, parallel::default_jobs(), |r| {
+                r.metadata(&Column::ALL)
+            });
+
+            let output = repositories_output(&repositories, &metadata, &Column::ALL);
+
+            match format {
+                Some(format) => output.print(&format),
+                None => {
+                    for (repo, metadata) in repositories.iter().zip(metadata.iter()) {
+                        println!("{}", repo.path_str());
+
+                        for (field, value) in [
+                            ("root", repo.root.clone()),
+                            ("host", repo.host.clone()),
+                            ("owner", repo.owner.clone()),
+                            ("name", repo.name.clone()),
+                        ]
+                        .into_iter()
+                        .chain(Column::ALL.map(|c| (c.name(), metadata.value(c))))
+                        {
+                            println!("    {:8}{}", field, value);
                         }
-                    } else {
-                        eprintln!("can not found the root folder '{}'", gpm_root)
                     }
                 }
             }
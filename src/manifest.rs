@@ -0,0 +1,541 @@
+#![deny(warnings)]
+
+use crate::clone::{self, CloneResult};
+use crate::format::Output;
+use crate::git;
+use crate::parallel;
+use crate::progress::Progress;
+use crate::remote;
+use crate::repository::{self, Repository};
+use eyre::Report;
+use serde::{Deserialize, Serialize};
+use serde_json::json;
+use std::path::{Path, PathBuf};
+
+// The version of manifest, it increases only when the fields change in a breaking way
+pub const VERSION: u32 = 1;
+
+// The repositories of workspace which can be restored on another machine
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq)]
+pub struct Manifest {
+    pub version: u32,
+    #[serde(rename = "repository", default)]
+    pub repositories: Vec<Entry>,
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq)]
+pub struct Entry {
+    // the path relative to root, separated by '/'. eg. github.com/axetroy/gpm.rs
+    pub path: String,
+    pub remote: String,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub branch: Option<String>,
+    // the pinned commit, it is checked out after clone
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub commit: Option<String>,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ManifestFormat {
+    Toml,
+    Json,
+}
+
+impl ManifestFormat {
+    // The format of manifest file by the extension, default to TOML
+    pub fn from_path(path: &Path) -> ManifestFormat {
+        match path.extension().and_then(|e| e.to_str()) {
+            Some("json") => ManifestFormat::Json,
+            _ => ManifestFormat::Toml,
+        }
+    }
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub enum RestoreResult {
+    Cloned,
+    // the repository exists and matches the manifest
+    Present,
+    // the repository exists but different with the manifest. eg. 'branch: main != develop'
+    Drifted(Vec<String>),
+    // the repository is not in the manifest
+    Extra,
+    // the repository is missing, it would be cloned without dry-run
+    Missing,
+    Failed(String),
+}
+
+impl RestoreResult {
+    pub const NAMES: [&'static str; 6] =
+        ["cloned", "present", "drifted", "extra", "missing", "failed"];
+
+    pub fn name(&self) -> &'static str {
+        match self {
+            RestoreResult::Cloned => "cloned",
+            RestoreResult::Present => "present",
+            RestoreResult::Drifted(_) => "drifted",
+            RestoreResult::Extra => "extra",
+            RestoreResult::Missing => "missing",
+            RestoreResult::Failed(_) => "failed",
+        }
+    }
+
+    pub fn detail(&self) -> Option<String> {
+        match self {
+            RestoreResult::Drifted(drifts) => Some(drifts.join(", ")),
+            RestoreResult::Failed(e) => Some(e.to_string()),
+            _ => None,
+        }
+    }
+}
+
+impl Manifest {
+    pub fn parse(content: &str, format: ManifestFormat) -> Result<Manifest, Report> {
+        let manifest: Manifest = match format {
+            ManifestFormat::Toml => toml::from_str(content)?,
+            ManifestFormat::Json => serde_json::from_str(content)?,
+        };
+
+        if manifest.version > VERSION {
+            return Err(Report::msg(format!(
+                "the manifest version {} is not supported, please upgrade gpm",
+                manifest.version
+            )));
+        }
+
+        Ok(manifest)
+    }
+
+    pub fn to_string(&self, format: ManifestFormat) -> Result<String, Report> {
+        Ok(match format {
+            ManifestFormat::Toml => toml::to_string(self)?,
+            ManifestFormat::Json => serde_json::to_string_pretty(self)? + "\n",
+        })
+    }
+}
+
+// The path relative to root, separated by '/'
+pub fn relative_path(repo: &Repository) -> String {
+    repo.path
+        .strip_prefix(&repo.root)
+        .unwrap_or(&repo.path)
+        .components()
+        .map(|c| c.as_os_str().to_string_lossy().to_string())
+        .collect::<Vec<String>>()
+        .join("/")
+}
+
+// The absolute path of entry in root
+fn entry_path(root: &str, entry: &Entry) -> PathBuf {
+    entry
+        .path
+        .split('/')
+        .fold(PathBuf::from(root), |path, name| path.join(name))
+}
+
+// The entry of repository, return None if it has no origin
+pub fn entry(repo: &Repository, pin: bool) -> Result<Option<Entry>, Report> {
+    let Some(remote) = git::remote_url(&repo.path, "origin") else {
+        return Ok(None);
+    };
+
+    // the manifest is shared, the token must not be written
+    let remote = remote::strip_credentials(&remote).unwrap_or(remote);
+
+    Ok(Some(Entry {
+        path: relative_path(repo),
+        remote,
+        branch: git::current_branch(&repo.path)?,
+        commit: if pin {
+            git::rev_parse(&repo.path, "HEAD")
+        } else {
+            None
+        },
+    }))
+}
+
+// The drifts of the existing repository. eg. 'branch: main != develop'
+pub fn drifts(dir: &Path, entry: &Entry) -> Vec<String> {
+    let mut drifts: Vec<String> = vec![];
+
+    let remote = git::remote_url(dir, "origin").map(|r| remote::strip_credentials(&r).unwrap_or(r));
+
+    if remote.as_deref() != Some(entry.remote.as_str()) {
+        drifts.push(format!(
+            "remote: {} != {}",
+            entry.remote,
+            remote.as_deref().unwrap_or("-")
+        ));
+    }
+
+    if let Some(branch) = &entry.branch {
+        let current = git::current_branch(dir).ok().flatten();
+
+        if current.as_deref() != Some(branch.as_str()) {
+            drifts.push(format!(
+                "branch: {} != {}",
+                branch,
+                current.as_deref().unwrap_or("-")
+            ));
+        }
+    }
+
+    if let Some(commit) = &entry.commit {
+        let head = git::rev_parse(dir, "HEAD");
+
+        if head.as_deref() != Some(commit.as_str()) {
+            drifts.push(format!(
+                "commit: {} != {}",
+                &commit[..commit.len().min(7)],
+                head.as_deref().map(|h| &h[..h.len().min(7)]).unwrap_or("-")
+            ));
+        }
+    }
+
+    drifts
+}
+
+// Checkout the branch and the pinned commit of the new clone.
+// The branch is put at the pinned commit, the remote branch may move after export.
+fn checkout(dir: &Path, entry: &Entry) -> Result<(), Report> {
+    match (&entry.branch, &entry.commit) {
+        (Some(branch), Some(commit)) => {
+            git::output(dir, &["checkout", "-q", "-B", branch, commit, "--"])?;
+        }
+        (Some(branch), None) => {
+            if git::current_branch(dir)?.as_deref() != Some(branch.as_str()) {
+                git::output(dir, &["checkout", "-q", branch, "--"])?;
+            }
+        }
+        (None, Some(commit)) => {
+            git::output(dir, &["checkout", "-q", "--detach", commit, "--"])?;
+        }
+        (None, None) => {}
+    }
+
+    Ok(())
+}
+
+// Restore the repository of entry, the missing one is cloned into the root
+fn restore_entry(
+    roots: &[String],
+    root: &str,
+    entry: &Entry,
+    options: &clone::Options,
+    dry_run: bool,
+) -> RestoreResult {
+    // the path can not be out of root, and must be walked by scan
+    if entry
+        .path
+        .split('/')
+        .any(|name| name.is_empty() || name == "." || name == ".." || name.contains('\\'))
+        || !repository::is_layout_path(Path::new(&entry.path))
+    {
+        return RestoreResult::Failed(format!("invalid path '{}'", entry.path));
+    }
+
+    // the values of manifest can not be read as the options of git
+    for value in [&entry.branch, &entry.commit].into_iter().flatten() {
+        if value.starts_with('-') {
+            return RestoreResult::Failed(format!("invalid ref '{}'", value));
+        }
+    }
+
+    // the repository may be in any root
+    if let Some(dir) = roots
+        .iter()
+        .map(|r| entry_path(r, entry))
+        .find(|p| p.exists())
+    {
+        return match drifts(&dir, entry) {
+            drifts if drifts.is_empty() => RestoreResult::Present,
+            drifts => RestoreResult::Drifted(drifts),
+        };
+    }
+
+    if dry_run {
+        return RestoreResult::Missing;
+    }
+
+    let dest = entry_path(root, entry);
+
+    match clone::clone_to(root, &entry.remote, dest.clone(), options) {
+        CloneResult::Cloned(_) => match checkout(&dest, entry) {
+            Ok(()) => RestoreResult::Cloned,
+            Err(e) => RestoreResult::Failed(format!("can not checkout: {}", e)),
+        },
+        CloneResult::Skipped(s) | CloneResult::Failed(s) => RestoreResult::Failed(s),
+    }
+}
+
+// Restore the repositories of manifest into the root, the existing ones in roots are checked for drift.
+// The results are in the order of manifest,
+// then the repositories of workspace which are not in the manifest
+pub fn restore(
+    manifest: &Manifest,
+    roots: &[String],
+    repositories: &[Repository],
+    root: &str,
+    options: &clone::Options,
+    dry_run: bool,
+) -> Vec<(String, RestoreResult)> {
+    let progress = Progress::new();
+
+    let results = parallel::map(&manifest.repositories, options.jobs, |entry| {
+        let id = progress.start(&format!("restoring {}", entry.path));
+
+        let result = restore_entry(roots, root, entry, options, dry_run);
+
+        let message = match result.detail() {
+            Some(detail) => format!("{:10} {} ({})", result.name(), entry.path, detail),
+            None => format!("{:10} {}", result.name(), entry.path),
+        };
+
+        progress.finish(id, &message);
+
+        (entry.path.clone(), result)
+    });
+
+    let extras = repositories
+        .iter()
+        .map(relative_path)
+        .filter(|path| !manifest.repositories.iter().any(|e| &e.path == path))
+        .map(|path| (path, RestoreResult::Extra));
+
+    results.into_iter().chain(extras).collect()
+}
+
+// The structured output of results
+pub fn output(results: &[(String, RestoreResult)]) -> Output {
+    let mut output = Output::new("restore", &["path", "result", "detail"]);
+
+    for (path, result) in results {
+        output.push(vec![
+            json!(path),
+            json!(result.name()),
+            json!(result.detail()),
+        ]);
+    }
+
+    output
+}
+
+// The report of results, the summary line and the repositories need attention
+pub fn report(results: &[(String, RestoreResult)]) -> Vec<String> {
+    let mut lines = vec![RestoreResult::NAMES
+        .iter()
+        .map(|name| {
+            let count = results.iter().filter(|(_, r)| r.name() == *name).count();
+            format!("{} {}", count, name)
+        })
+        .collect::<Vec<String>>()
+        .join(", ")];
+
+    for (path, result) in results {
+        match result {
+            RestoreResult::Cloned | RestoreResult::Present => {}
+            result => lines.push(match result.detail() {
+                Some(detail) => format!("{}: {} ({})", result.name(), path, detail),
+                None => format!("{}: {}", result.name(), path),
+            }),
+        }
+    }
+
+    lines
+}
+
+#[cfg(test)]
+mod tests {
+    use std::fs;
+
+    use crate::clone::{self, Conflict};
+    use crate::git;
+    use crate::manifest::{self, Entry, Manifest, ManifestFormat, RestoreResult};
+    use crate::network;
+    use crate::repository::Repository;
+    use crate::testing;
+
+    #[test]
+    fn test_manifest_format() {
+        let m1 = Manifest {
+            version: manifest::VERSION,
+            repositories: vec![Entry {
+                path: "github.com/axetroy/gpm.rs".to_string(),
+                remote: "https://github.com/axetroy/gpm.rs".to_string(),
+                branch: Some("main".to_string()),
+                commit: None,
+            }],
+        };
+
+        let toml = m1.to_string(ManifestFormat::Toml).unwrap();
+
+        assert_eq!(
+            toml,
+            "version = 1\n\n[[repository]]\npath = \"github.com/axetroy/gpm.rs\"\nremote = \"https://github.com/axetroy/gpm.rs\"\nbranch = \"main\"\n"
+        );
+        assert_eq!(Manifest::parse(&toml, ManifestFormat::Toml).unwrap(), m1);
+
+        let json = m1.to_string(ManifestFormat::Json).unwrap();
+
+        assert_eq!(Manifest::parse(&json, ManifestFormat::Json).unwrap(), m1);
+        assert!(Manifest::parse("version = 2", ManifestFormat::Toml).is_err());
+        assert_eq!(
+            ManifestFormat::from_path(std::path::Path::new("gpm.json")),
+            ManifestFormat::Json
+        );
+
+        let r1 = manifest::restore(
+            &Manifest::parse(
+                "version = 1\n[[repository]]\npath = '../a'\nremote = 'https://github.com/a/b'",
+                ManifestFormat::Toml,
+            )
+            .unwrap(),
+            &["/root".to_string()],
+            &[],
+            "/root",
+            &clone::Options {
+                jobs: 1,
+                host_jobs: 1,
+                conflict: Conflict::Skip,
+                args: vec![],
+                network: network::Options::default(),
+                strip_credentials: false,
+            },
+            true,
+        );
+
+        assert_eq!(
+            r1[0].1,
+            RestoreResult::Failed("invalid path '../a'".to_string())
+        );
+    }
+
+    #[test]
+    fn test_export_and_restore() {
+        let remote = testing::init_bare_repo("manifest-remote");
+        let root = testing::temp_dir("manifest-root");
+        let roots = vec![root.to_str().unwrap().to_string()];
+        let root_str = &roots[0];
+
+        let url = remote.to_str().unwrap();
+
+        let repo = root.join("example.com").join("axetroy").join("a");
+
+        testing::git(&root, &["clone", "-q", url, repo.to_str().unwrap()]);
+        testing::commit(&repo, "local");
+
+        let a = vec![Repository::new(root_str, repo.clone())];
+
+        let entry = manifest::entry(&a[0], true).unwrap().unwrap();
+
+        assert_eq!(entry.path, "example.com/axetroy/a");
+        assert_eq!(entry.remote, url);
+        assert_eq!(entry.branch.as_deref(), Some("main"));
+
+        let options = clone::Options {
+            jobs: 2,
+            host_jobs: 1,
+            conflict: Conflict::Skip,
+            args: vec![],
+            network: network::Options::default(),
+            strip_credentials: false,
+        };
+
+        // the pinned commit of origin, the branch of another path
+        let init = git::rev_parse(&repo, "HEAD~1").unwrap();
+
+        let m1 = Manifest {
+            version: manifest::VERSION,
+            repositories: vec![
+                entry,
+                Entry {
+                    path: "example.com/axetroy/b".to_string(),
+                    remote: url.to_string(),
+                    branch: Some("main".to_string()),
+                    commit: Some(init.clone()),
+                },
+            ],
+        };
+
+        let r1 = manifest::restore(&m1, &roots, &a, root_str, &options, true);
+
+        assert_eq!(
+            r1,
+            vec![
+                ("example.com/axetroy/a".to_string(), RestoreResult::Present),
+                ("example.com/axetroy/b".to_string(), RestoreResult::Missing),
+            ]
+        );
+
+        // the remote branch moves after export
+        testing::git(&repo, &["push", "-q", "origin", "main"]);
+
+        let r2 = manifest::restore(&m1, &roots, &a, root_str, &options, false);
+
+        assert_eq!(r2[1].1, RestoreResult::Cloned);
+
+        let b = root.join("example.com").join("axetroy").join("b");
+
+        assert_eq!(git::rev_parse(&b, "HEAD").unwrap(), init);
+        assert_eq!(git::current_branch(&b).unwrap().as_deref(), Some("main"));
+
+        testing::commit(&repo, "drift");
+
+        let b_repo = Repository::new(root_str, b);
+        let r3 = manifest::restore(
+            &m1,
+            &roots,
+            &[a[0].clone(), b_repo],
+            root_str,
+            &options,
+            true,
+        );
+
+        assert!(matches!(&r3[0].1, RestoreResult::Drifted(d) if d[0].starts_with("commit: ")));
+        assert_eq!(r3[1].1, RestoreResult::Present);
+
+        let invalid = |path: &str, branch: &str| Entry {
+            path: path.to_string(),
+            remote: url.to_string(),
+            branch: Some(branch.to_string()),
+            commit: None,
+        };
+
+        let m2 = Manifest {
+            version: manifest::VERSION,
+            repositories: vec![
+                invalid(".gpm-trash/axetroy/c", "main"),
+                invalid("example.com/c", "main"),
+                invalid("example.com/axetroy/c/d", "main"),
+                invalid("example.com/axetroy/c", "--orphan=x"),
+            ],
+        };
+
+        for (_, result) in manifest::restore(&m2, &roots, &[], root_str, &options, false) {
+            assert!(matches!(result, RestoreResult::Failed(_)), "{:?}", result);
+        }
+
+        let r4 = manifest::restore(
+            &Manifest {
+                version: manifest::VERSION,
+                repositories: vec![],
+            },
+            &roots,
+            &[Repository::new(root_str, repo)],
+            root_str,
+            &options,
+            true,
+        );
+
+        assert_eq!(
+            manifest::report(&r4),
+            vec![
+                "0 cloned, 0 present, 0 drifted, 1 extra, 0 missing, 0 failed",
+                "extra: example.com/axetroy/a"
+            ]
+        );
+
+        fs::remove_dir_all(remote).unwrap();
+        fs::remove_dir_all(root).unwrap();
+    }
+}
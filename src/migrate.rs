@@ -0,0 +1,291 @@
+#![deny(warnings)]
+
+use crate::format::Output;
+use crate::git;
+use crate::relocate;
+use crate::remote::{self, RemoteUrl};
+use crate::repository::Repository;
+use eyre::Report;
+use serde_json::json;
+use std::path::PathBuf;
+
+// The rule to remap the owner or group. eg. old-team=new-group/team
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Rule {
+    pub from: String,
+    pub to: String,
+}
+
+impl Rule {
+    pub fn parse(rule: &str) -> Result<Rule, Report> {
+        match rule.split_once('=') {
+            Some((from, to)) if !from.trim().is_empty() && !to.trim().is_empty() => Ok(Rule {
+                from: from.trim().trim_matches('/').to_string(),
+                to: to.trim().trim_matches('/').to_string(),
+            }),
+            _ => Err(Report::msg(format!(
+                "invalid owner rule '{}', eg. old-owner=new-owner",
+                rule
+            ))),
+        }
+    }
+}
+
+// What would be changed in the repository
+#[derive(Debug, Clone, PartialEq)]
+pub struct Plan {
+    // the config key, the old URL and the new URL. eg. remote.origin.url
+    pub urls: Vec<(String, String, String)>,
+    // the location computed from the new origin
+    pub dest: PathBuf,
+}
+
+// Rewrite the URL of old host to the new host, return None if it is not of the old host
+pub fn rewrite(url: &str, old_host: &str, new_host: &str, rules: &[Rule]) -> Option<String> {
+    let mut remote = RemoteUrl::parse(url)?;
+
+    if !remote.host.eq_ignore_ascii_case(old_host) {
+        return None;
+    }
+
+    remote.host = new_host.to_string();
+
+    // the longest rule first, the sub group is more specific
+    let mut rules = rules.iter().collect::<Vec<&Rule>>();
+    rules.sort_by_key(|r| std::cmp::Reverse(r.from.len()));
+
+    if let Some(rule) = rules.iter().find(|r| {
+        remote
+            .path
+            .strip_prefix(&r.from)
+            .is_some_and(|rest| rest.starts_with('/'))
+    }) {
+        remote.path = format!("{}{}", rule.to, &remote.path[rule.from.len()..]);
+    }
+
+    Some(remote.to_string())
+}
+
+// Plan the migration of repository, return None if no remote of the old host
+pub fn plan(
+    repo: &Repository,
+    old_host: &str,
+    new_host: &str,
+    rules: &[Rule],
+) -> Result<Option<Plan>, Report> {
+    let urls = remote::config_urls(&repo.path)?
+        .into_iter()
+        .filter_map(|(key, url)| {
+            rewrite(&url, old_host, new_host, rules).map(|new_url| (key, url, new_url))
+        })
+        .collect::<Vec<(String, String, String)>>();
+
+    if urls.is_empty() {
+        return Ok(None);
+    }
+
+    let dest = match urls.iter().find(|(key, _, _)| key == "remote.origin.url") {
+        Some((_, _, origin)) => git::url_to_path(&repo.root, origin)?,
+        None => repo.path.clone(),
+    };
+
+    Ok(Some(Plan { urls, dest }))
+}
+
+// Rewrite the remotes, then move the repository to the new location.
+// The remotes are restored if the repository can not be moved.
+pub fn apply(repo: &Repository, plan: &Plan) -> Result<(), Report> {
+    remote::rewrite_config_urls(&repo.path, &plan.urls)?;
+
+    if plan.dest == repo.path {
+        return Ok(());
+    }
+
+    match relocate::move_to(repo, &plan.dest) {
+        Ok(()) => Ok(()),
+        // the folder is moved, only the empty parents are not removed
+        Err(e) if !repo.path.exists() => Err(e),
+        Err(e) => {
+            let rollback = plan
+                .urls
+                .iter()
+                .map(|(key, old, new)| (key.clone(), new.clone(), old.clone()))
+                .collect::<Vec<(String, String, String)>>();
+
+            match remote::rewrite_config_urls(&repo.path, &rollback) {
+                Ok(()) => Err(e),
+                Err(re) => Err(Report::msg(format!(
+                    "{}, and can not restore the remotes: {}",
+                    e, re
+                ))),
+            }
+        }
+    }
+}
+
+// The structured output of the migration
+pub fn output(repositories: &[Repository], plans: &[Plan], results: &[&str]) -> Output {
+    let fields = Repository::FIELDS
+        .into_iter()
+        .chain(["dest", "remotes", "result"])
+        .collect::<Vec<&str>>();
+
+    let mut output = Output::new("migrate-host", &fields).with_tree_levels(3);
+
+    for ((repo, plan), result) in repositories.iter().zip(plans).zip(results) {
+        let mut record = repo.fields();
+
+        record.extend([
+            json!(plan.dest.display().to_string()),
+            json!(plan
+                .urls
+                .iter()
+                .map(|(key, old, new)| json!({
+                    "remote": remote::remote_name(key),
+                    "old": old,
+                    "new": new,
+                }))
+                .collect::<Vec<_>>()),
+            json!(result),
+        ]);
+
+        output.push(record);
+    }
+
+    output
+}
+
+#[cfg(test)]
+mod tests {
+    use std::fs;
+
+    use crate::git;
+    use crate::migrate::{self, Rule};
+    use crate::repository::Repository;
+    use crate::testing;
+
+    #[test]
+    fn test_rewrite() {
+        let rules = vec![
+            Rule::parse("team=platform/team").unwrap(),
+            Rule::parse("team/sub=sub").unwrap(),
+        ];
+
+        let old = "git.oldcorp.com";
+        let new = "gitlab.newcorp.com";
+
+        assert_eq!(
+            migrate::rewrite("https://git.oldcorp.com/team/api.git", old, new, &rules).as_deref(),
+            Some("https://gitlab.newcorp.com/platform/team/api.git")
+        );
+        assert_eq!(
+            migrate::rewrite("git@git.oldcorp.com:team/sub/web.git", old, new, &rules).as_deref(),
+            Some("git@gitlab.newcorp.com:sub/web.git")
+        );
+        assert_eq!(
+            migrate::rewrite(
+                "ssh://git@Git.OldCorp.com:2222/teams/x.git",
+                old,
+                new,
+                &rules
+            )
+            .as_deref(),
+            Some("ssh://git@gitlab.newcorp.com:2222/teams/x.git")
+        );
+        assert_eq!(
+            migrate::rewrite("https://github.com/team/api.git", old, new, &rules),
+            None
+        );
+        assert!(Rule::parse("team").is_err());
+    }
+
+    #[test]
+    fn test_plan_and_apply() {
+        let root = testing::temp_dir("migrate-root");
+        let root_str = root.to_str().unwrap();
+
+        let path = root.join("git.oldcorp.com").join("team").join("api");
+
+        fs::create_dir_all(&path).unwrap();
+        testing::git(&path, &["init", "-q", "-b", "main"]);
+        testing::git(
+            &path,
+            &[
+                "remote",
+                "add",
+                "origin",
+                "https://git.oldcorp.com/team/api.git",
+            ],
+        );
+        testing::git(
+            &path,
+            &[
+                "remote",
+                "add",
+                "upstream",
+                "https://github.com/team/api.git",
+            ],
+        );
+
+        let repo = Repository::new(root_str, path.clone());
+        let rules = vec![Rule::parse("team=platform").unwrap()];
+
+        let plan = migrate::plan(&repo, "git.oldcorp.com", "gitlab.newcorp.com", &rules)
+            .unwrap()
+            .unwrap();
+
+        let dest = root.join("gitlab.newcorp.com").join("platform").join("api");
+
+        assert_eq!(plan.urls.len(), 1);
+        assert_eq!(plan.dest, dest);
+
+        migrate::apply(&repo, &plan).unwrap();
+
+        assert!(!root.join("git.oldcorp.com").exists());
+        assert_eq!(
+            git::remote_url(&dest, "origin").as_deref(),
+            Some("https://gitlab.newcorp.com/platform/api.git")
+        );
+        assert_eq!(
+            git::remote_url(&dest, "upstream").as_deref(),
+            Some("https://github.com/team/api.git")
+        );
+
+        let moved = Repository::new(root_str, dest);
+
+        assert_eq!(
+            migrate::plan(&moved, "git.oldcorp.com", "gitlab.newcorp.com", &rules).unwrap(),
+            None
+        );
+
+        // the remotes are restored if the destination is taken
+        let web = root.join("git.oldcorp.com").join("team").join("web");
+
+        fs::create_dir_all(&web).unwrap();
+        fs::create_dir_all(root.join("gitlab.newcorp.com").join("platform").join("web")).unwrap();
+        testing::git(&web, &["init", "-q", "-b", "main"]);
+        testing::git(
+            &web,
+            &[
+                "remote",
+                "add",
+                "origin",
+                "https://git.oldcorp.com/team/web.git",
+            ],
+        );
+
+        let repo = Repository::new(root_str, web.clone());
+
+        let plan = migrate::plan(&repo, "git.oldcorp.com", "gitlab.newcorp.com", &rules)
+            .unwrap()
+            .unwrap();
+
+        assert!(migrate::apply(&repo, &plan).is_err());
+        assert_eq!(
+            git::remote_url(&web, "origin").as_deref(),
+            Some("https://git.oldcorp.com/team/web.git")
+        );
+
+        fs::remove_dir_all(root).unwrap();
+    }
+}
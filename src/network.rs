@@ -0,0 +1,564 @@
+#![deny(warnings)]
+
+use crate::remote;
+use crate::util;
+use clap::{Arg, ArgMatches};
+use eyre::Report;
+use std::env;
+use std::fmt;
+use std::io::{self, Read, Write};
+use std::path::Path;
+use std::process::{Child, Command as ChildProcess, Stdio};
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::mpsc::{self, Receiver};
+use std::sync::{Arc, Mutex};
+use std::thread;
+use std::time::{Duration, Instant};
+
+// Set by Ctrl-C, the running git processes are killed and the remaining are not started
+static CANCELLED: AtomicBool = AtomicBool::new(false);
+
+// The first delay before retry, it is doubled for the next retry
+const BACKOFF: Duration = Duration::from_secs(1);
+const MAX_BACKOFF: Duration = Duration::from_secs(30);
+
+// The options of git commands which talk to the remote
+#[derive(Debug, Clone, PartialEq)]
+pub struct Options {
+    // kill the git process if it is not finished in the duration
+    pub timeout: Option<Duration>,
+    // the count of retries for the transient failures. eg. network unreachable
+    pub retries: u32,
+    // allow git to prompt for the credentials in terminal
+    pub interactive: bool,
+}
+
+impl Default for Options {
+    fn default() -> Options {
+        Options {
+            timeout: None,
+            retries: 2,
+            interactive: false,
+        }
+    }
+}
+
+impl Options {
+    pub fn from_matches(matches: &ArgMatches, interactive: bool) -> Result<Options, Report> {
+        let timeout = match matches.value_of("timeout") {
+            Some(v) => Some(util::parse_duration(v)?),
+            None => None,
+        };
+
+        let retries = match matches.value_of("retries") {
+            Some(v) => v
+                .parse::<u32>()
+                .map_err(|_| Report::msg(format!("invalid retries '{}'", v)))?,
+            None => Options::default().retries,
+        };
+
+        Ok(Options {
+            timeout,
+            retries,
+            interactive,
+        })
+    }
+}
+
+// The arguments of timeout and retries for the commands which talk to the remote
+pub fn args<'help>() -> Vec<Arg<'help>> {
+    vec![
+        Arg::new("timeout")
+            .long("timeout")
+            .help("Kill the git process if it is not finished in the duration. eg. 30s, 10m")
+            .takes_value(true)
+            .validator(|v| util::parse_duration(v).map(|_| ())),
+        Arg::new("retries")
+            .long("retries")
+            .help("The count of retries for network failures, default to 2")
+            .takes_value(true)
+            .validator(|v| {
+                v.parse::<u32>()
+                    .map(|_| ())
+                    .map_err(|_| format!("invalid retries '{}', expect a number", v))
+            }),
+    ]
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ErrorKind {
+    // the failure of network, it may succeed if retry
+    Transient,
+    Fatal,
+    TimedOut,
+    Cancelled,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct Error {
+    pub kind: ErrorKind,
+    pub message: String,
+}
+
+impl fmt::Display for Error {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}", self.message)
+    }
+}
+
+impl std::error::Error for Error {}
+
+// Classify the failure by the stderr of git
+pub fn classify(stderr: &str) -> ErrorKind {
+    let stderr = stderr.to_lowercase();
+
+    // these are failed for the same reason if retry
+    let fatal = [
+        "authentication failed",
+        "permission denied",
+        "repository not found",
+        "does not exist",
+        "could not read username",
+        "could not read password",
+        "terminal prompts disabled",
+        "host key verification failed",
+        "returned error: 401",
+        "returned error: 403",
+        "returned error: 404",
+    ];
+
+    let transient = [
+        "could not resolve host",
+        "temporary failure in name resolution",
+        "connection timed out",
+        "operation timed out",
+        "connection reset",
+        "connection refused",
+        "network is unreachable",
+        "the remote end hung up unexpectedly",
+        "unexpected disconnect",
+        "early eof",
+        "rpc failed",
+        "gnutls",
+        "ssl_error",
+        "returned error: 429",
+        "returned error: 500",
+        "returned error: 502",
+        "returned error: 503",
+        "returned error: 504",
+    ];
+
+    if fatal.iter().any(|s| stderr.contains(s)) {
+        ErrorKind::Fatal
+    } else if transient.iter().any(|s| stderr.contains(s)) {
+        ErrorKind::Transient
+    } else {
+        ErrorKind::Fatal
+    }
+}
+
+// Kill the git processes when Ctrl-C, call it once before running the commands
+pub fn handle_interrupt() {
+    ctrlc::set_handler(|| CANCELLED.store(true, Ordering::SeqCst))
+        .unwrap_or_else(|e| eprintln!("Error setting Ctrl-C handler: {}", e));
+}
+
+pub fn is_cancelled() -> bool {
+    CANCELLED.load(Ordering::SeqCst)
+}
+
+// The output of pipe which is read in background
+struct Pipe {
+    content: Arc<Mutex<Vec<u8>>>,
+    closed: Receiver<()>,
+}
+
+impl Pipe {
+    // Read the pipe in background, the content is also printed to stderr if echo
+    fn read<R>(mut pipe: R, echo: bool) -> Pipe
+    where
+        R: Read + Send + 'static,
+    {
+        let content = Arc::new(Mutex::new(vec![]));
+        let writer = content.clone();
+        let (sender, closed) = mpsc::channel();
+
+        // it is not joined, the process started by git may keep the pipe open after git exited
+        thread::spawn(move || {
+            let mut buf = [0u8; 4096];
+
+            while let Ok(n) = pipe.read(&mut buf) {
+                if n == 0 {
+                    break;
+                }
+
+                if echo {
+                    io::stderr().write_all(&buf[..n]).ok();
+                }
+
+                writer.lock().unwrap().extend_from_slice(&buf[..n]);
+            }
+
+            sender.send(()).ok();
+        });
+
+        Pipe { content, closed }
+    }
+
+    // The content after the pipe is closed, or what is read in a moment
+    fn text(self) -> String {
+        self.closed.recv_timeout(Duration::from_secs(1)).ok();
+
+        let content = self.content.lock().unwrap();
+
+        String::from_utf8_lossy(&content).to_string()
+    }
+}
+
+// Kill git and the processes started by it if it is the leader of process group. eg. git-remote-https
+fn kill(child: &mut Child, group: bool) {
+    if group {
+        #[cfg(unix)]
+        unsafe {
+            libc::kill(-(child.id() as libc::pid_t), libc::SIGKILL);
+        }
+    }
+
+    child.kill().ok();
+    child.wait().ok();
+}
+
+// Wait for the process with the timeout, kill it if timed out or cancelled
+fn wait(child: &mut Child, timeout: Option<Duration>, group: bool) -> Result<bool, ErrorKind> {
+    let start = Instant::now();
+
+    loop {
+        if let Ok(Some(status)) = child.try_wait() {
+            return Ok(status.success());
+        }
+
+        let kind = if is_cancelled() {
+            Some(ErrorKind::Cancelled)
+        } else if timeout.is_some_and(|t| start.elapsed() >= t) {
+            Some(ErrorKind::TimedOut)
+        } else {
+            None
+        };
+
+        if let Some(kind) = kind {
+            kill(child, group);
+            return Err(kind);
+        }
+
+        thread::sleep(Duration::from_millis(50));
+    }
+}
+
+// The ssh command which never prompts. The env overrides core.sshCommand, so the configured one is kept.
+// eg. ssh -i ~/.ssh/work -o BatchMode=yes
+fn batch_ssh_command(dir: Option<&Path>) -> String {
+    let mut process = ChildProcess::new("git");
+
+    if let Some(dir) = dir {
+        process.arg("-C").arg(dir);
+    }
+
+    let configured = process
+        .args(["config", "core.sshCommand"])
+        .stdin(Stdio::null())
+        .stderr(Stdio::null())
+        .output()
+        .ok()
+        .filter(|output| output.status.success())
+        .map(|output| String::from_utf8_lossy(&output.stdout).trim().to_string())
+        .filter(|command| !command.is_empty());
+
+    format!(
+        "{} -o BatchMode=yes",
+        configured.as_deref().unwrap_or("ssh")
+    )
+}
+
+// Run the git command once and return the stdout
+fn run_once(
+    dir: Option<&Path>,
+    args: &[&str],
+    options: &Options,
+    echo: bool,
+) -> Result<String, Error> {
+    // the token in URL should not be printed
+    let command = format!(
+        "git {}",
+        args.iter()
+            .map(|a| remote::mask_credentials(a))
+            .collect::<Vec<String>>()
+            .join(" ")
+    );
+
+    if is_cancelled() {
+        return Err(Error {
+            kind: ErrorKind::Cancelled,
+            message: format!("{} is cancelled", command),
+        });
+    }
+
+    let mut process = ChildProcess::new("git");
+
+    if let Some(dir) = dir {
+        process.arg("-C").arg(dir);
+    }
+
+    process
+        .args(args)
+        .stdout(Stdio::piped())
+        .stderr(Stdio::piped());
+
+    if options.interactive {
+        process.stdin(Stdio::inherit());
+    } else {
+        // fail instead of waiting for the input forever
+        process.stdin(Stdio::null()).env("GIT_TERMINAL_PROMPT", "0");
+
+        if env::var_os("GIT_SSH_COMMAND").is_none() && env::var_os("GIT_SSH").is_none() {
+            process.env("GIT_SSH_COMMAND", batch_ssh_command(dir));
+        }
+
+        // the processes started by git are killed together, it can not read the terminal anymore
+        #[cfg(unix)]
+        std::os::unix::process::CommandExt::process_group(&mut process, 0);
+    }
+
+    let mut child = process.spawn().map_err(|e| Error {
+        kind: ErrorKind::Fatal,
+        message: format!("can not run {}: {}", command, e),
+    })?;
+
+    let stdout = Pipe::read(child.stdout.take().unwrap(), false);
+    let stderr = Pipe::read(child.stderr.take().unwrap(), echo);
+
+    match wait(&mut child, options.timeout, !options.interactive) {
+        Ok(true) => Ok(stdout.text().trim_end().to_string()),
+        Ok(false) => {
+            let stderr = stderr.text().trim().to_string();
+
+            // the stderr is printed already if echo
+            let message = if echo {
+                format!("{} process fail", command)
+            } else {
+                format!("{} process fail: {}", command, stderr)
+            };
+
+            Err(Error {
+                kind: classify(&stderr),
+                message,
+            })
+        }
+        Err(ErrorKind::TimedOut) => Err(Error {
+            kind: ErrorKind::TimedOut,
+            message: format!(
+                "{} is timed out after {}s",
+                command,
+                options.timeout.unwrap_or_default().as_secs()
+            ),
+        }),
+        Err(kind) => Err(Error {
+            kind,
+            message: format!("{} is cancelled", command),
+        }),
+    }
+}
+
+// Sleep for the backoff, return false if cancelled
+fn backoff(delay: Duration) -> bool {
+    let start = Instant::now();
+
+    while start.elapsed() < delay {
+        if is_cancelled() {
+            return false;
+        }
+
+        thread::sleep(Duration::from_millis(50));
+    }
+
+    true
+}
+
+// Run the git command, retry with backoff for the transient failures and timeout.
+// The `before_retry` is called with the error before the next retry. eg. clean the partial clone
+pub fn run<F>(
+    dir: Option<&Path>,
+    args: &[&str],
+    options: &Options,
+    echo: bool,
+    mut before_retry: F,
+) -> Result<String, Report>
+where
+    F: FnMut(&Error, Duration),
+{
+    let mut delay = BACKOFF;
+    let mut attempt = 0;
+
+    loop {
+        match run_once(dir, args, options, echo) {
+            Ok(stdout) => return Ok(stdout),
+            Err(e) => {
+                let retryable = matches!(e.kind, ErrorKind::Transient | ErrorKind::TimedOut);
+
+                if !retryable || attempt >= options.retries {
+                    return Err(e.into());
+                }
+
+                before_retry(&e, delay);
+
+                if !backoff(delay) {
+                    return Err(Error {
+                        kind: ErrorKind::Cancelled,
+                        message: e.message,
+                    }
+                    .into());
+                }
+
+                attempt += 1;
+                delay = (delay * 2).min(MAX_BACKOFF);
+            }
+        }
+    }
+}
+
+// Run the git command in the repository folder and return the stdout, retry silently
+pub fn output(dir: &Path, args: &[&str], options: &Options) -> Result<String, Report> {
+    run(Some(dir), args, options, false, |_, _| {})
+}
+
+#[cfg(test)]
+mod tests {
+    use std::fs;
+    use std::time::{Duration, Instant};
+
+    use crate::network::{self, ErrorKind, Options};
+    use crate::testing;
+
+    #[test]
+    fn test_classify() {
+        assert_eq!(
+            network::classify("fatal: unable to access 'https://github.com/a/b/': Could not resolve host: github.com"),
+            ErrorKind::Transient
+        );
+        assert_eq!(
+            network::classify(
+                "error: RPC failed; curl 56 GnuTLS recv error (-9)\nfatal: early EOF"
+            ),
+            ErrorKind::Transient
+        );
+        assert_eq!(
+            network::classify("remote: Repository not found.\nfatal: repository 'https://github.com/a/b/' not found"),
+            ErrorKind::Fatal
+        );
+        assert_eq!(
+            network::classify("fatal: could not read Username for 'https://github.com': terminal prompts disabled"),
+            ErrorKind::Fatal
+        );
+        assert_eq!(
+            network::classify("git@github.com: Permission denied (publickey).\nfatal: Could not read from remote repository."),
+            ErrorKind::Fatal
+        );
+    }
+
+    #[test]
+    fn test_output() {
+        let repo = testing::init_repo("network-output");
+
+        let options = Options::default();
+
+        assert_eq!(
+            network::output(&repo, &["rev-parse", "--is-inside-work-tree"], &options).unwrap(),
+            "true"
+        );
+
+        let r1 = network::output(&repo, &["fetch", "not-exist-remote"], &options).unwrap_err();
+
+        assert_eq!(
+            r1.downcast_ref::<network::Error>().unwrap().kind,
+            ErrorKind::Fatal
+        );
+
+        fs::remove_dir_all(repo).unwrap();
+    }
+
+    // the fake ssh is a shell script
+    #[cfg(unix)]
+    #[test]
+    fn test_configured_ssh_command() {
+        use std::os::unix::fs::PermissionsExt;
+
+        let repo = testing::init_repo("network-ssh");
+        let log = repo.join("ssh.log");
+        let script = repo.join("fake-ssh");
+
+        fs::write(
+            &script,
+            format!(
+                "#!/bin/sh\necho \"$@\" > '{}'\necho 'Permission denied (publickey).' >&2\nexit 1\n",
+                log.display()
+            ),
+        )
+        .unwrap();
+
+        fs::set_permissions(&script, fs::Permissions::from_mode(0o755)).unwrap();
+
+        testing::git(
+            &repo,
+            &["config", "core.sshCommand", script.to_str().unwrap()],
+        );
+
+        assert!(network::output(
+            &repo,
+            &["ls-remote", "ssh://git@gpm.test/axetroy/gpm.rs"],
+            &Options::default()
+        )
+        .is_err());
+
+        // the configured command runs without prompt
+        assert!(fs::read_to_string(&log)
+            .unwrap()
+            .starts_with("-o BatchMode=yes"));
+
+        fs::remove_dir_all(repo).unwrap();
+    }
+
+    #[test]
+    fn test_timeout_and_retry() {
+        let repo = testing::init_repo("network-timeout");
+
+        let options = Options {
+            timeout: Some(Duration::from_millis(200)),
+            retries: 1,
+            interactive: false,
+        };
+
+        let mut retries = 0;
+        let start = Instant::now();
+
+        let r1 = network::run(
+            Some(&repo),
+            &["-c", "alias.hang=!sleep 5", "hang"],
+            &options,
+            false,
+            |e, delay| {
+                assert_eq!(e.kind, ErrorKind::TimedOut);
+                assert_eq!(delay, Duration::from_secs(1));
+                retries += 1;
+            },
+        )
+        .unwrap_err();
+
+        assert_eq!(retries, 1);
+        assert_eq!(
+            r1.downcast_ref::<network::Error>().unwrap().kind,
+            ErrorKind::TimedOut
+        );
+        assert!(start.elapsed() < Duration::from_secs(4));
+
+        fs::remove_dir_all(repo).unwrap();
+    }
+}
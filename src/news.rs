@@ -0,0 +1,399 @@
+#![deny(warnings)]
+
+use crate::format::Output;
+use crate::git;
+use crate::repository::Repository;
+use eyre::Report;
+use serde::{Deserialize, Serialize};
+use serde_json::json;
+use std::collections::HashMap;
+use std::fs;
+use std::path::{Path, PathBuf};
+use std::time::{SystemTime, UNIX_EPOCH};
+
+// The max count of fetches kept in the record of repository
+const MAX_RECORDS: usize = 50;
+
+// The ref changed by fetch, None means the ref does not exist
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq)]
+pub struct RefChange {
+    pub name: String,
+    pub before: Option<String>,
+    pub after: Option<String>,
+}
+
+// The changes of refs in one fetch
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq)]
+pub struct FetchRecord {
+    pub time: u64,
+    pub changes: Vec<RefChange>,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub enum Kind {
+    NewBranch,
+    Update(Vec<(String, String)>),
+    ForcePush(Vec<(String, String)>),
+    DeletedBranch,
+    NewTag,
+    MovedTag,
+    DeletedTag,
+}
+
+// The digest of a ref change
+#[derive(Debug, Clone, PartialEq)]
+pub struct News {
+    pub time: u64,
+    pub change: RefChange,
+    pub kind: Kind,
+}
+
+fn record_file(dir: &Path) -> Result<PathBuf, Report> {
+    Ok(git::git_dir(dir)?.join("gpm").join("news.json"))
+}
+
+fn now() -> u64 {
+    SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .unwrap()
+        .as_secs()
+}
+
+// Compare the refs before and after fetch
+pub fn diff(before: &[(String, String)], after: &[(String, String)]) -> Vec<RefChange> {
+    // the repository may have thousands of tags
+    fn index(refs: &[(String, String)]) -> HashMap<&str, &str> {
+        refs.iter()
+            .map(|(name, id)| (name.as_str(), id.as_str()))
+            .collect()
+    }
+
+    let before_ids = index(before);
+    let after_ids = index(after);
+
+    let mut changes: Vec<RefChange> = vec![];
+
+    for (name, id) in after {
+        let old = before_ids.get(name.as_str()).map(|id| id.to_string());
+
+        if old.as_ref() != Some(id) {
+            changes.push(RefChange {
+                name: name.to_string(),
+                before: old,
+                after: Some(id.to_string()),
+            });
+        }
+    }
+
+    for (name, id) in before {
+        if !after_ids.contains_key(name.as_str()) {
+            changes.push(RefChange {
+                name: name.to_string(),
+                before: Some(id.to_string()),
+                after: None,
+            });
+        }
+    }
+
+    changes
+}
+
+// Read the fetch records of repository, the newest is the last
+pub fn read(dir: &Path) -> Result<Vec<FetchRecord>, Report> {
+    if !git::is_repository(dir) {
+        return Ok(vec![]);
+    }
+
+    let file = record_file(dir)?;
+
+    if !file.exists() {
+        return Ok(vec![]);
+    }
+
+    Ok(serde_json::from_str(&fs::read_to_string(file)?)?)
+}
+
+// Append the changes of fetch into the record of repository
+pub fn record(dir: &Path, changes: Vec<RefChange>) -> Result<(), Report> {
+    if changes.is_empty() {
+        return Ok(());
+    }
+
+    let mut records = read(dir).unwrap_or_default();
+
+    records.push(FetchRecord {
+        time: now(),
+        changes,
+    });
+
+    if records.len() > MAX_RECORDS {
+        records.drain(..records.len() - MAX_RECORDS);
+    }
+
+    let file = record_file(dir)?;
+
+    fs::create_dir_all(file.parent().unwrap())?;
+    fs::write(file, serde_json::to_string(&records)?)?;
+
+    Ok(())
+}
+
+// The digest of the ref change
+pub fn classify(dir: &Path, change: &RefChange) -> Kind {
+    let is_tag = change.name.starts_with("refs/tags/");
+
+    match (&change.before, &change.after, is_tag) {
+        (None, _, false) => Kind::NewBranch,
+        (None, _, true) => Kind::NewTag,
+        (Some(_), None, false) => Kind::DeletedBranch,
+        (Some(_), None, true) => Kind::DeletedTag,
+        (Some(_), Some(_), true) => Kind::MovedTag,
+        (Some(before), Some(after), false) => {
+            let commits =
+                git::log_subjects(dir, &format!("{}..{}", before, after)).unwrap_or_default();
+
+            if git::is_ancestor(dir, before, after) {
+                Kind::Update(commits)
+            } else {
+                Kind::ForcePush(commits)
+            }
+        }
+    }
+}
+
+// The news of repository in the recent fetches. If `since` is None, only the last fetch
+pub fn collect(dir: &Path, since: Option<u64>) -> Result<Vec<News>, Report> {
+    let records = read(dir)?;
+
+    let records: Vec<&FetchRecord> = match since {
+        Some(seconds) => {
+            let from = now().saturating_sub(seconds);
+            records.iter().filter(|r| r.time >= from).collect()
+        }
+        None => records.last().into_iter().collect(),
+    };
+
+    Ok(records
+        .into_iter()
+        .flat_map(|r| {
+            r.changes.iter().map(|change| News {
+                time: r.time,
+                change: change.clone(),
+                kind: classify(dir, change),
+            })
+        })
+        .collect())
+}
+
+impl News {
+    // The short name of ref. eg. origin/main, v1.0.0
+    pub fn ref_name(&self) -> &str {
+        let name = &self.change.name;
+
+        name.strip_prefix("refs/remotes/")
+            .or_else(|| name.strip_prefix("refs/tags/"))
+            .unwrap_or(name)
+    }
+
+    pub fn kind_name(&self) -> &'static str {
+        match self.kind {
+            Kind::NewBranch => "new-branch",
+            Kind::Update(_) => "update",
+            Kind::ForcePush(_) => "force-push",
+            Kind::DeletedBranch => "deleted-branch",
+            Kind::NewTag => "new-tag",
+            Kind::MovedTag => "moved-tag",
+            Kind::DeletedTag => "deleted-tag",
+        }
+    }
+
+    fn commits(&self) -> &[(String, String)] {
+        match &self.kind {
+            Kind::Update(commits) | Kind::ForcePush(commits) => commits,
+            _ => &[],
+        }
+    }
+
+    // The lines for printing
+    pub fn lines(&self) -> Vec<String> {
+        let short = |id: &Option<String>| {
+            id.as_ref()
+                .map(|s| s.chars().take(7).collect::<String>())
+                .unwrap_or_default()
+        };
+
+        let commits = |n: usize| format!("{} new {}", n, if n == 1 { "commit" } else { "commits" });
+
+        let title = match &self.kind {
+            Kind::NewBranch => format!(
+                "{}: new branch at {}",
+                self.ref_name(),
+                short(&self.change.after)
+            ),
+            Kind::Update(list) => format!("{}: {}", self.ref_name(), commits(list.len())),
+            Kind::ForcePush(list) => format!(
+                "{}: force-pushed {}...{}, {}",
+                self.ref_name(),
+                short(&self.change.before),
+                short(&self.change.after),
+                commits(list.len())
+            ),
+            Kind::DeletedBranch => format!("{}: deleted", self.ref_name()),
+            Kind::NewTag => format!("tag {}: new", self.ref_name()),
+            Kind::MovedTag => format!(
+                "tag {}: moved {}...{}",
+                self.ref_name(),
+                short(&self.change.before),
+                short(&self.change.after)
+            ),
+            Kind::DeletedTag => format!("tag {}: deleted", self.ref_name()),
+        };
+
+        std::iter::once(title)
+            .chain(
+                self.commits()
+                    .iter()
+                    .map(|(id, subject)| format!("    {} {}", &id[..7.min(id.len())], subject)),
+            )
+            .collect()
+    }
+}
+
+// The structured output of news
+pub fn output(news: &[(Repository, Vec<News>)]) -> Output {
+    let fields = Repository::FIELDS
+        .into_iter()
+        .chain(["time", "ref", "kind", "before", "after", "commits"])
+        .collect::<Vec<&str>>();
+
+    let mut output = Output::new("news", &fields).with_tree_levels(3);
+
+    for (repo, items) in news {
+        for item in items {
+            let mut record = repo.fields();
+
+            record.extend([
+                json!(item.time),
+                json!(item.ref_name()),
+                json!(item.kind_name()),
+                json!(item.change.before),
+                json!(item.change.after),
+                json!(item
+                    .commits()
+                    .iter()
+                    .map(|(id, subject)| json!({ "id": id, "subject": subject }))
+                    .collect::<Vec<_>>()),
+            ]);
+
+            output.push(record);
+        }
+    }
+
+    output
+}
+
+#[cfg(test)]
+mod tests {
+    use std::fs;
+
+    use crate::git;
+    use crate::network;
+    use crate::news::{self, Kind, RefChange};
+    use crate::sync;
+    use crate::testing;
+
+    #[test]
+    fn test_diff() {
+        let before = vec![
+            ("refs/remotes/origin/main".to_string(), "a".to_string()),
+            ("refs/remotes/origin/old".to_string(), "b".to_string()),
+        ];
+        let after = vec![
+            ("refs/remotes/origin/main".to_string(), "c".to_string()),
+            ("refs/tags/v1".to_string(), "d".to_string()),
+        ];
+
+        assert_eq!(
+            news::diff(&before, &after),
+            vec![
+                RefChange {
+                    name: "refs/remotes/origin/main".to_string(),
+                    before: Some("a".to_string()),
+                    after: Some("c".to_string()),
+                },
+                RefChange {
+                    name: "refs/tags/v1".to_string(),
+                    before: None,
+                    after: Some("d".to_string()),
+                },
+                RefChange {
+                    name: "refs/remotes/origin/old".to_string(),
+                    before: Some("b".to_string()),
+                    after: None,
+                },
+            ]
+        );
+
+        assert!(news::diff(&before, &before).is_empty());
+    }
+
+    #[test]
+    fn test_news_after_fetch() {
+        let remote = testing::init_bare_repo("news-remote");
+        let writer = testing::clone_repo(&remote, "news-writer");
+        let repo = testing::clone_repo(&remote, "news-repo");
+
+        assert!(news::collect(&repo, None).unwrap().is_empty());
+
+        testing::commit(&writer, "first");
+        testing::commit(&writer, "second");
+        testing::git(&writer, &["tag", "v1.0.0"]);
+        testing::git(&writer, &["push", "-q", "--tags", "origin", "main"]);
+
+        sync::fetch(&repo, &network::Options::default());
+
+        let r1 = news::collect(&repo, None).unwrap();
+
+        assert_eq!(r1.len(), 2);
+        assert_eq!(r1[0].ref_name(), "origin/main");
+        assert!(matches!(&r1[0].kind, Kind::Update(commits) if commits.len() == 2));
+        assert_eq!(
+            r1[0].lines()[1..],
+            [
+                format!(
+                    "    {} second",
+                    &git::rev_parse(&writer, "HEAD~0").unwrap()[..7]
+                ),
+                format!(
+                    "    {} first",
+                    &git::rev_parse(&writer, "HEAD~1").unwrap()[..7]
+                ),
+            ]
+        );
+        assert_eq!(r1[1].ref_name(), "v1.0.0");
+        assert_eq!(r1[1].kind, Kind::NewTag);
+
+        testing::git(&writer, &["reset", "-q", "--hard", "HEAD~1"]);
+        testing::commit(&writer, "rewrite");
+        testing::git(&writer, &["push", "-q", "-f", "origin", "main"]);
+
+        sync::fetch(&repo, &network::Options::default());
+
+        let r2 = news::collect(&repo, None).unwrap();
+
+        assert_eq!(r2.len(), 1);
+        assert!(matches!(&r2[0].kind, Kind::ForcePush(commits) if commits.len() == 1));
+
+        // the digest of recent fetches is kept
+        assert_eq!(news::collect(&repo, Some(3600)).unwrap().len(), 3);
+
+        // nothing changed, the last digest is kept
+        sync::fetch(&repo, &network::Options::default());
+
+        assert_eq!(news::collect(&repo, None).unwrap(), r2);
+
+        fs::remove_dir_all(remote).unwrap();
+        fs::remove_dir_all(writer).unwrap();
+        fs::remove_dir_all(repo).unwrap();
+    }
+}
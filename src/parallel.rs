@@ -0,0 +1,228 @@
+#![deny(warnings)]
+
+use clap::{Arg, ArgMatches};
+use std::collections::{HashMap, VecDeque};
+use std::sync::atomic::{AtomicUsize, Ordering};
+use std::sync::{Condvar, Mutex};
+use std::thread;
+
+// The default count of workers, same as the available parallelism
+pub fn default_jobs() -> usize {
+    thread::available_parallelism()
+        .map(|n| n.get())
+        .unwrap_or(4)
+}
+
+// The argument of workers count for the commands which run in parallel
+pub fn arg<'help>() -> Arg<'help> {
+    Arg::new("jobs")
+        .short('J')
+        .long("jobs")
+        .help("The number of repositories to process in parallel, default to the number of CPUs")
+        .takes_value(true)
+        .validator(|v| match v.parse::<usize>() {
+            Ok(n) if n > 0 => Ok(()),
+            _ => Err(format!("invalid jobs '{}', expect a positive number", v)),
+        })
+}
+
+// The count of workers from the argument that defined in `arg()`
+pub fn jobs_from_matches(matches: &ArgMatches) -> usize {
+    matches
+        .value_of("jobs")
+        .and_then(|v| v.parse::<usize>().ok())
+        .unwrap_or_else(default_jobs)
+}
+
+// Map the items with a pool of workers, the results keep the order of items
+pub fn map<T, R, F>(items: &[T], jobs: usize, f: F) -> Vec<R>
+where
+    T: Sync,
+    R: Send,
+    F: Fn(&T) -> R + Sync,
+{
+    let next = AtomicUsize::new(0);
+    let results: Mutex<Vec<Option<R>>> = Mutex::new(items.iter().map(|_| None).collect());
+    let workers = jobs.clamp(1, items.len().max(1));
+
+    thread::scope(|scope| {
+        for _ in 0..workers {
+            scope.spawn(|| loop {
+                let index = next.fetch_add(1, Ordering::SeqCst);
+
+                if index >= items.len() {
+                    break;
+                }
+
+                let result = f(&items[index]);
+
+                results.lock().unwrap()[index] = Some(result);
+            });
+        }
+    });
+
+    results
+        .into_inner()
+        .unwrap()
+        .into_iter()
+        .map(|r| r.expect("worker did not finish"))
+        .collect()
+}
+
+// The pending items and the count of running items of each key
+struct Queue {
+    pending: VecDeque<usize>,
+    running: HashMap<String, usize>,
+}
+
+// The item taken from the queue, the count of its key is released when dropped even if panic
+struct Running<'a> {
+    index: usize,
+    key: &'a str,
+    queue: &'a Mutex<Queue>,
+    released: &'a Condvar,
+}
+
+impl Drop for Running<'_> {
+    fn drop(&mut self) {
+        if let Ok(mut queue) = self.queue.lock() {
+            if let Some(count) = queue.running.get_mut(self.key) {
+                *count -= 1;
+            }
+        }
+
+        self.released.notify_all();
+    }
+}
+
+// Map the items like `map`, but at most `limit` items of the same key run at the same time. eg. the host of repository.
+// The worker takes the first item whose key is not saturated, a busy key does not block the others.
+pub fn map_keyed<T, R, K, F>(items: &[T], jobs: usize, limit: usize, key: K, f: F) -> Vec<R>
+where
+    T: Sync,
+    R: Send,
+    K: Fn(&T) -> String,
+    F: Fn(&T) -> R + Sync,
+{
+    let keys = items.iter().map(key).collect::<Vec<String>>();
+    let limit = limit.max(1);
+    let queue = Mutex::new(Queue {
+        pending: (0..items.len()).collect(),
+        running: HashMap::new(),
+    });
+    let released = Condvar::new();
+    let results: Mutex<Vec<Option<R>>> = Mutex::new(items.iter().map(|_| None).collect());
+    let workers = jobs.clamp(1, items.len().max(1));
+
+    // wait until an item can run, None if all are taken
+    let take = || {
+        let mut guard = queue.lock().unwrap();
+
+        loop {
+            let Queue { pending, running } = &mut *guard;
+
+            if pending.is_empty() {
+                return None;
+            }
+
+            let position = pending
+                .iter()
+                .position(|&i| running.get(&keys[i]).copied().unwrap_or(0) < limit);
+
+            if let Some(index) = position.and_then(|p| pending.remove(p)) {
+                *running.entry(keys[index].clone()).or_insert(0) += 1;
+
+                return Some(Running {
+                    index,
+                    key: &keys[index],
+                    queue: &queue,
+                    released: &released,
+                });
+            }
+
+            guard = released.wait(guard).unwrap();
+        }
+    };
+
+    thread::scope(|scope| {
+        for _ in 0..workers {
+            scope.spawn(|| {
+                while let Some(running) = take() {
+                    let result = f(&items[running.index]);
+
+                    results.lock().unwrap()[running.index] = Some(result);
+                }
+            });
+        }
+    });
+
+    results
+        .into_inner()
+        .unwrap()
+        .into_iter()
+        .map(|r| r.expect("worker did not finish"))
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::parallel;
+    use std::sync::atomic::{AtomicUsize, Ordering};
+    use std::thread;
+    use std::time::Duration;
+
+    #[test]
+    fn test_map_keep_order() {
+        let items: Vec<u32> = (0..100).collect();
+
+        let r1 = parallel::map(&items, 8, |n| n * 2);
+
+        assert_eq!(r1, (0..100).map(|n| n * 2).collect::<Vec<u32>>());
+    }
+
+    #[test]
+    fn test_map_keyed() {
+        let github = AtomicUsize::new(0);
+        let max_github = AtomicUsize::new(0);
+        let github_done = AtomicUsize::new(0);
+
+        // the items of the busy host are in front
+        let items = ["github.com"; 4]
+            .into_iter()
+            .chain(["gitlab.com"; 4])
+            .collect::<Vec<&str>>();
+
+        let r1 = parallel::map_keyed(
+            &items,
+            4,
+            1,
+            |host| host.to_string(),
+            |host| {
+                if *host != "github.com" {
+                    // the workers are not blocked by the busy host
+                    return github_done.load(Ordering::SeqCst);
+                }
+
+                let count = github.fetch_add(1, Ordering::SeqCst) + 1;
+                max_github.fetch_max(count, Ordering::SeqCst);
+                thread::sleep(Duration::from_millis(50));
+                github.fetch_sub(1, Ordering::SeqCst);
+                github_done.fetch_add(1, Ordering::SeqCst);
+
+                0
+            },
+        );
+
+        assert_eq!(r1, vec![0; 8]);
+        assert_eq!(max_github.load(Ordering::SeqCst), 1);
+    }
+
+    #[test]
+    fn test_map_empty() {
+        let items: Vec<u32> = vec![];
+
+        let r1 = parallel::map(&items, 0, |n| n * 2);
+
+        assert!(r1.is_empty());
+    }
+}
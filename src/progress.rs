@@ -0,0 +1,79 @@
+#![deny(warnings)]
+
+use std::io::{self, IsTerminal, Write};
+use std::sync::Mutex;
+
+// The live progress with a line for each running task, drawn to stderr.
+// The finished messages are printed above the running lines and kept.
+pub struct Progress {
+    live: bool,
+    state: Mutex<State>,
+}
+
+#[derive(Default)]
+struct State {
+    next_id: usize,
+    running: Vec<(usize, String)>,
+    drawn: usize,
+}
+
+impl Progress {
+    // The running lines are only drawn if stderr is terminal
+    pub fn new() -> Progress {
+        Progress {
+            live: io::stderr().is_terminal(),
+            state: Mutex::new(State::default()),
+        }
+    }
+
+    // Add a running line, return the id to finish it
+    pub fn start(&self, message: &str) -> usize {
+        let mut state = self.state.lock().unwrap();
+
+        let id = state.next_id;
+        state.next_id += 1;
+        state.running.push((id, message.to_string()));
+
+        self.draw(&mut state, None);
+
+        id
+    }
+
+    // Remove the running line and print the finished message
+    pub fn finish(&self, id: usize, message: &str) {
+        let mut state = self.state.lock().unwrap();
+
+        state.running.retain(|(i, _)| *i != id);
+
+        self.draw(&mut state, Some(message));
+    }
+
+    fn draw(&self, state: &mut State, message: Option<&str>) {
+        let stderr = io::stderr();
+        let mut stderr = stderr.lock();
+
+        if !self.live {
+            if let Some(message) = message {
+                writeln!(stderr, "{}", message).ok();
+            }
+            return;
+        }
+
+        // clear the running lines drawn last time
+        for _ in 0..state.drawn {
+            write!(stderr, "\x1b[1A\x1b[2K").ok();
+        }
+
+        if let Some(message) = message {
+            writeln!(stderr, "{}", message).ok();
+        }
+
+        for (_, line) in &state.running {
+            writeln!(stderr, "{}", line).ok();
+        }
+
+        state.drawn = state.running.len();
+
+        stderr.flush().ok();
+    }
+}
@@ -0,0 +1,180 @@
+#![deny(warnings)]
+
+use crate::format::Output;
+use crate::git;
+use crate::remove;
+use crate::repository::Repository;
+use crate::staging;
+use eyre::Report;
+use serde_json::json;
+use std::fs;
+use std::path::{Path, PathBuf};
+
+// The repository whose location does not match the origin. eg. the repository is renamed or transferred
+#[derive(Debug, Clone, PartialEq)]
+pub struct Mismatch {
+    pub origin: String,
+    // the location computed from the origin
+    pub dest: PathBuf,
+}
+
+// Whether the folder is the copy cloned by 'Auto'. eg. gpm.rs(1) of gpm.rs
+fn is_renamed_copy(path: &Path, dest: &Path) -> bool {
+    if path.parent() != dest.parent() {
+        return false;
+    }
+
+    let (Some(name), Some(dest_name)) = (path.file_name(), dest.file_name()) else {
+        return false;
+    };
+
+    let name = name.to_string_lossy();
+
+    name.strip_prefix(dest_name.to_string_lossy().as_ref())
+        .and_then(|rest| rest.strip_prefix('('))
+        .and_then(|rest| rest.strip_suffix(')'))
+        .is_some_and(|index| !index.is_empty() && index.chars().all(|c| c.is_ascii_digit()))
+}
+
+// Compare the origin with the location, return None if matched or no origin
+pub fn check(repo: &Repository) -> Result<Option<Mismatch>, Report> {
+    let Some(origin) = git::remote_url(&repo.path, "origin") else {
+        return Ok(None);
+    };
+
+    let dest = git::url_to_path(&repo.root, &origin)?;
+
+    if dest == repo.path || is_renamed_copy(&repo.path, &dest) {
+        return Ok(None);
+    }
+
+    Ok(Some(Mismatch { origin, dest }))
+}
+
+// Move the repository to another location of the same root, the empty parents are removed
+pub fn move_to(repo: &Repository, dest: &Path) -> Result<(), Report> {
+    let _lock = staging::lock_destination(&repo.root, dest)?.ok_or_else(|| {
+        Report::msg(format!(
+            "another gpm is cloning into '{}' now",
+            dest.display()
+        ))
+    })?;
+
+    // the folder may only differ in case on case-insensitive file system
+    let same_folder =
+        dest.exists() && fs::canonicalize(dest).ok() == fs::canonicalize(&repo.path).ok();
+
+    if dest.exists() && !same_folder {
+        return Err(Report::msg(format!(
+            "the folder '{}' already exists",
+            dest.display()
+        )));
+    }
+
+    if let Some(parent) = dest.parent() {
+        fs::create_dir_all(parent)?;
+    }
+
+    fs::rename(&repo.path, dest)?;
+
+    remove::prune_empty_parents(Path::new(&repo.root), &repo.path)?;
+
+    Ok(())
+}
+
+// The structured output of the mismatched repositories
+pub fn output(repositories: &[Repository], mismatches: &[Mismatch], results: &[&str]) -> Output {
+    let fields = Repository::FIELDS
+        .into_iter()
+        .chain(["origin", "dest", "result"])
+        .collect::<Vec<&str>>();
+
+    let mut output = Output::new("relocate", &fields).with_tree_levels(3);
+
+    for ((repo, mismatch), result) in repositories.iter().zip(mismatches).zip(results) {
+        let mut record = repo.fields();
+
+        record.extend([
+            json!(mismatch.origin),
+            json!(mismatch.dest.display().to_string()),
+            json!(result),
+        ]);
+
+        output.push(record);
+    }
+
+    output
+}
+
+#[cfg(test)]
+mod tests {
+    use std::fs;
+
+    use crate::relocate::{self, Mismatch};
+    use crate::repository::Repository;
+    use crate::testing;
+
+    #[test]
+    fn test_check_and_move() {
+        let root = testing::temp_dir("relocate-root");
+        let root_str = root.to_str().unwrap();
+
+        let path = root.join("github.com").join("axetroy").join("gpm");
+
+        fs::create_dir_all(&path).unwrap();
+        testing::git(&path, &["init", "-q", "-b", "main"]);
+        testing::git(
+            &path,
+            &[
+                "remote",
+                "add",
+                "origin",
+                "https://github.com/axetroy/gpm.rs",
+            ],
+        );
+
+        let repo = Repository::new(root_str, path.clone());
+        let dest = root.join("github.com").join("axetroy").join("gpm.rs");
+
+        assert!(relocate::is_renamed_copy(
+            &root.join("github.com").join("axetroy").join("gpm.rs(1)"),
+            &dest
+        ));
+        assert!(!relocate::is_renamed_copy(&path, &dest));
+
+        assert_eq!(
+            relocate::check(&repo).unwrap(),
+            Some(Mismatch {
+                origin: "https://github.com/axetroy/gpm.rs".to_string(),
+                dest: dest.clone(),
+            })
+        );
+
+        fs::create_dir_all(&dest).unwrap();
+
+        assert!(relocate::move_to(&repo, &dest).is_err());
+
+        fs::remove_dir(&dest).unwrap();
+
+        let transferred = root.join("github.com").join("gpmer").join("gpm.rs");
+
+        relocate::move_to(&repo, &transferred).unwrap();
+
+        assert!(transferred.join(".git").exists());
+        assert!(!path.exists());
+        // the empty folder of owner is removed
+        assert!(!root.join("github.com").join("axetroy").exists());
+
+        let moved = Repository::new(root_str, transferred.clone());
+
+        relocate::move_to(&moved, &dest).unwrap();
+
+        assert!(!root.join("github.com").join("gpmer").exists());
+        assert_eq!(
+            relocate::check(&Repository::new(root_str, dest)).unwrap(),
+            None
+        );
+
+        fs::remove_dir_all(root).unwrap();
+    }
+}
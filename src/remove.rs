@@ -0,0 +1,263 @@
+#![deny(warnings)]
+
+use crate::format::Output;
+use crate::git;
+use crate::repository::Repository;
+use crate::trash;
+use eyre::Report;
+use serde_json::json;
+use std::fs;
+use std::path::Path;
+
+// The work which would be lost if the repository is removed
+#[derive(Debug, Default, Clone, PartialEq)]
+pub struct Loss {
+    // the uncommitted changes of tracked files. eg. 'M src/main.rs'
+    pub changes: Vec<String>,
+    // the untracked files, the ignored files are not included
+    pub untracked: Vec<String>,
+    // eg. 'stash@{0}: WIP on main: 1234567 init'
+    pub stashes: Vec<String>,
+    // the local branches with the count of commits not in any remote
+    pub unpushed: Vec<(String, u32)>,
+    pub error: Option<String>,
+}
+
+impl Loss {
+    pub fn check(dir: &Path) -> Loss {
+        if !git::is_repository(dir) {
+            return Loss {
+                error: Some("not a git repository".to_string()),
+                ..Loss::default()
+            };
+        }
+
+        let lines = |args: &[&str]| -> Result<Vec<String>, Report> {
+            Ok(git::output(dir, args)?
+                .lines()
+                .map(|line| line.trim().to_string())
+                .collect())
+        };
+
+        let result = (|| -> Result<Loss, Report> {
+            Ok(Loss {
+                changes: lines(&["status", "--porcelain", "--untracked-files=no"])?,
+                untracked: lines(&["ls-files", "--others", "--exclude-standard"])?,
+                stashes: lines(&["stash", "list"])?,
+                unpushed: git::unpushed_branches(dir)?,
+                error: None,
+            })
+        })();
+
+        result.unwrap_or_else(|e| Loss {
+            error: Some(e.to_string()),
+            ..Loss::default()
+        })
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.changes.is_empty()
+            && self.untracked.is_empty()
+            && self.stashes.is_empty()
+            && self.unpushed.is_empty()
+            && self.error.is_none()
+    }
+
+    // The lines for printing what would be lost
+    pub fn lines(&self) -> Vec<String> {
+        let mut lines: Vec<String> = vec![];
+
+        if let Some(e) = &self.error {
+            lines.push(format!("error: {}", e));
+        }
+
+        for change in &self.changes {
+            lines.push(format!("uncommitted: {}", change));
+        }
+
+        for file in &self.untracked {
+            lines.push(format!("untracked: {}", file));
+        }
+
+        for stash in &self.stashes {
+            lines.push(format!("stash: {}", stash));
+        }
+
+        for (branch, count) in &self.unpushed {
+            lines.push(format!(
+                "unpushed: {} ({} {})",
+                branch,
+                count,
+                if *count == 1 { "commit" } else { "commits" }
+            ));
+        }
+
+        lines
+    }
+}
+
+// The path must be strictly inside the root, or the folders above the root would be removed
+fn check_inside(root: &Path, path: &Path) -> Result<(), Report> {
+    if root.as_os_str().is_empty() || path == root || !path.starts_with(root) {
+        return Err(Report::msg(format!(
+            "'{}' is not inside the root folder '{}'",
+            path.display(),
+            root.display()
+        )));
+    }
+
+    Ok(())
+}
+
+// Remove the empty parent folders of the path, until the root
+pub fn prune_empty_parents(root: &Path, path: &Path) -> Result<(), Report> {
+    check_inside(root, path)?;
+
+    let mut dir = path.parent();
+
+    while let Some(parent) = dir {
+        if parent == root || !parent.starts_with(root) {
+            break;
+        }
+
+        if fs::read_dir(parent)?.next().is_some() {
+            break;
+        }
+
+        fs::remove_dir(parent)?;
+
+        dir = parent.parent();
+    }
+
+    Ok(())
+}
+
+// Move the repository folder into trash and remove the empty owner and host folders
+pub fn remove(repo: &Repository) -> Result<trash::Entry, Report> {
+    check_inside(Path::new(&repo.root), &repo.path)?;
+
+    let entry = trash::trash(&repo.root, &repo.path)?;
+
+    prune_empty_parents(Path::new(&repo.root), &repo.path)?;
+
+    Ok(entry)
+}
+
+// The structured output of the repositories to remove
+pub fn output(repositories: &[Repository], losses: &[Loss], results: &[&str]) -> Output {
+    let fields = Repository::FIELDS
+        .into_iter()
+        .chain([
+            "result",
+            "uncommitted",
+            "untracked",
+            "stashes",
+            "unpushed",
+            "error",
+        ])
+        .collect::<Vec<&str>>();
+
+    let mut output = Output::new("remove", &fields).with_tree_levels(3);
+
+    for ((repo, loss), result) in repositories.iter().zip(losses.iter()).zip(results.iter()) {
+        let mut record = repo.fields();
+
+        record.extend([
+            json!(result),
+            json!(loss.changes),
+            json!(loss.untracked),
+            json!(loss.stashes),
+            json!(loss
+                .unpushed
+                .iter()
+                .map(|(branch, count)| json!({ "branch": branch, "commits": count }))
+                .collect::<Vec<_>>()),
+            json!(loss.error),
+        ]);
+
+        output.push(record);
+    }
+
+    output
+}
+
+#[cfg(test)]
+mod tests {
+    use std::fs;
+    use std::path::Path;
+
+    use crate::remove::{self, Loss};
+    use crate::repository::Repository;
+    use crate::testing;
+    use crate::trash;
+
+    #[test]
+    fn test_loss() {
+        let remote = testing::init_bare_repo("remove-remote");
+        let repo = testing::clone_repo(&remote, "remove-repo");
+
+        assert!(Loss::check(&repo).is_empty());
+
+        fs::write(repo.join("init"), "changed").unwrap();
+        fs::write(repo.join("new_file"), "").unwrap();
+        fs::write(repo.join(".gitignore"), "target\n").unwrap();
+        fs::write(repo.join("target"), "").unwrap();
+
+        let r1 = Loss::check(&repo);
+
+        assert_eq!(r1.changes, vec!["M init"]);
+        assert_eq!(r1.untracked, vec![".gitignore", "new_file"]);
+
+        testing::git(&repo, &["stash", "-q"]);
+        testing::git(&repo, &["checkout", "-q", "-b", "feature"]);
+        testing::commit(&repo, "feature");
+
+        let r2 = Loss::check(&repo);
+
+        assert!(r2.changes.is_empty());
+        assert_eq!(r2.stashes.len(), 1);
+        assert_eq!(r2.unpushed, vec![("feature".to_string(), 1)]);
+        assert_eq!(r2.lines().last().unwrap(), "unpushed: feature (1 commit)");
+
+        let not_repo = testing::temp_dir("remove-not-repo");
+
+        assert!(Loss::check(&not_repo).error.is_some());
+
+        fs::remove_dir_all(remote).unwrap();
+        fs::remove_dir_all(repo).unwrap();
+        fs::remove_dir_all(not_repo).unwrap();
+    }
+
+    #[test]
+    fn test_remove_and_prune() {
+        let root = testing::temp_dir("remove-root");
+
+        let a = root.join("github.com").join("axetroy").join("a");
+        let b = root.join("github.com").join("axetroy").join("b");
+
+        fs::create_dir_all(&a).unwrap();
+        fs::create_dir_all(&b).unwrap();
+
+        remove::remove(&Repository::new(root.to_str().unwrap(), a.clone())).unwrap();
+
+        assert!(!a.exists());
+        assert!(b.exists());
+
+        remove::remove(&Repository::new(root.to_str().unwrap(), b.clone())).unwrap();
+
+        assert!(!root.join("github.com").exists());
+        assert_eq!(trash::list(&[root.to_str().unwrap().to_string()]).len(), 2);
+
+        // the folders above the root are never touched
+        let c = root.join("github.com").join("axetroy").join("c");
+
+        fs::create_dir_all(&c).unwrap();
+
+        assert!(remove::prune_empty_parents(Path::new(""), &c).is_err());
+        assert!(remove::prune_empty_parents(&c, &root).is_err());
+        assert!(remove::remove(&Repository::new("", c.clone())).is_err());
+        assert!(c.exists());
+
+        fs::remove_dir_all(root).unwrap();
+    }
+}
@@ -0,0 +1,362 @@
+#![deny(warnings)]
+
+use crate::{git, util, walker};
+use eyre::Report;
+use serde::Serialize;
+use serde_json::{json, Value};
+use std::path::{Component, Path, PathBuf};
+
+// The repository cloned into the layout of root: $ROOT/host/owner/name
+#[derive(Debug, Clone, PartialEq)]
+pub struct Repository {
+    pub root: String,
+    pub path: PathBuf,
+    pub host: String,
+    pub owner: String,
+    pub name: String,
+}
+
+// The optional column of repository which computed by git
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Column {
+    Branch,
+    Status,
+    Ahead,
+    Behind,
+    Date,
+    Author,
+    Origin,
+    Size,
+}
+
+#[derive(Serialize, Debug, Default, Clone, PartialEq)]
+pub struct Metadata {
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub branch: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub status: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub ahead: Option<u32>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub behind: Option<u32>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub date: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub author: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub origin: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub size: Option<u64>,
+}
+
+impl Column {
+    pub const ALL: [Column; 8] = [
+        Column::Branch,
+        Column::Status,
+        Column::Ahead,
+        Column::Behind,
+        Column::Date,
+        Column::Author,
+        Column::Origin,
+        Column::Size,
+    ];
+
+    pub fn name(&self) -> &'static str {
+        match self {
+            Column::Branch => "branch",
+            Column::Status => "status",
+            Column::Ahead => "ahead",
+            Column::Behind => "behind",
+            Column::Date => "date",
+            Column::Author => "author",
+            Column::Origin => "origin",
+            Column::Size => "size",
+        }
+    }
+
+    pub fn parse(name: &str) -> Result<Column, Report> {
+        Column::ALL
+            .into_iter()
+            .find(|c| c.name() == name)
+            .ok_or_else(|| Report::msg(format!("unknown column '{}'", name)))
+    }
+}
+
+impl Metadata {
+    // The column value for printing, '-' if not available
+    pub fn value(&self, column: Column) -> String {
+        let value = match column {
+            Column::Branch => self.branch.clone(),
+            Column::Status => self.status.clone(),
+            Column::Ahead => self.ahead.map(|n| n.to_string()),
+            Column::Behind => self.behind.map(|n| n.to_string()),
+            Column::Date => self.date.clone(),
+            Column::Author => self.author.clone(),
+            Column::Origin => self.origin.clone(),
+            Column::Size => self.size.map(util::format_size),
+        };
+
+        value.unwrap_or_else(|| "-".to_string())
+    }
+
+    // The column value for structured output, null if not available
+    pub fn json(&self, column: Column) -> Value {
+        match column {
+            Column::Branch => json!(self.branch),
+            Column::Status => json!(self.status),
+            Column::Ahead => json!(self.ahead),
+            Column::Behind => json!(self.behind),
+            Column::Date => json!(self.date),
+            Column::Author => json!(self.author),
+            Column::Origin => json!(self.origin),
+            Column::Size => json!(self.size),
+        }
+    }
+}
+
+impl Repository {
+    pub fn new(root: &str, path: PathBuf) -> Repository {
+        let name_of = |p: Option<&Path>| {
+            p.and_then(|p| p.file_name())
+                .map(|s| s.to_string_lossy().to_string())
+                .unwrap_or_default()
+        };
+
+        let owner_dir = path.parent();
+        let host_dir = owner_dir.and_then(|p| p.parent());
+
+        Repository {
+            root: root.to_string(),
+            host: name_of(host_dir),
+            owner: name_of(owner_dir),
+            name: name_of(Some(&path)),
+            path,
+        }
+    }
+
+    // The fields to locate the repository in structured output
+    pub const FIELDS: [&'static str; 5] = ["root", "host", "owner", "name", "path"];
+
+    pub fn fields(&self) -> Vec<Value> {
+        vec![
+            json!(self.root),
+            json!(self.host),
+            json!(self.owner),
+            json!(self.name),
+            json!(self.path_str()),
+        ]
+    }
+
+    pub fn path_str(&self) -> &str {
+        self.path.as_os_str().to_str().unwrap()
+    }
+
+    // Compute the columns of repository, the failed column is left empty
+    pub fn metadata(&self, columns: &[Column]) -> Metadata {
+        let mut metadata = Metadata::default();
+        let dir = self.path.as_path();
+
+        // do not let git look up the parent folder
+        if !git::is_repository(dir) {
+            return metadata;
+        }
+
+        if columns.contains(&Column::Branch) {
+            metadata.branch = git::current_branch(dir).ok().flatten();
+        }
+
+        if columns.contains(&Column::Status) {
+            metadata.status = git::is_dirty(dir)
+                .ok()
+                .map(|dirty| if dirty { "dirty" } else { "clean" }.to_string());
+        }
+
+        if columns.contains(&Column::Ahead) || columns.contains(&Column::Behind) {
+            if let Ok(Some((ahead, behind))) = git::ahead_behind(dir) {
+                if columns.contains(&Column::Ahead) {
+                    metadata.ahead = Some(ahead);
+                }
+                if columns.contains(&Column::Behind) {
+                    metadata.behind = Some(behind);
+                }
+            }
+        }
+
+        if columns.contains(&Column::Date) || columns.contains(&Column::Author) {
+            if let Ok(Some((date, author))) = git::last_commit(dir) {
+                if columns.contains(&Column::Date) {
+                    metadata.date = Some(date);
+                }
+                if columns.contains(&Column::Author) {
+                    metadata.author = Some(author);
+                }
+            }
+        }
+
+        if columns.contains(&Column::Origin) {
+            metadata.origin = git::remote_url(dir, "origin");
+        }
+
+        if columns.contains(&Column::Size) {
+            metadata.size = util::dir_size(dir).ok();
+        }
+
+        metadata
+    }
+}
+
+// Whether the relative path is host/owner/name which is walked by scan.
+// The hidden folders of root are used by gpm. eg. .gpm-trash
+pub fn is_layout_path(relative: &Path) -> bool {
+    let names = relative
+        .components()
+        .map(|c| match c {
+            Component::Normal(name) => Some(name.to_string_lossy()),
+            _ => None,
+        })
+        .collect::<Option<Vec<_>>>();
+
+    match names {
+        Some(names) => names.len() == 3 && !names[0].starts_with('.'),
+        None => false,
+    }
+}
+
+// The repository of the absolute path, None if it is not at $ROOT/host/owner/name of any root
+pub fn locate(roots: &[String], path: &Path) -> Option<Repository> {
+    roots
+        .iter()
+        .filter(|root| !root.is_empty())
+        .find(|root| match path.strip_prefix(root) {
+            Ok(relative) => is_layout_path(relative),
+            Err(_) => false,
+        })
+        .map(|root| Repository::new(root, path.to_path_buf()))
+}
+
+// Find the repositories of all roots, the root which does not exist will be reported
+pub fn scan(roots: &[String]) -> Vec<Repository> {
+    let mut repositories: Vec<Repository> = vec![];
+
+    for gpm_root in roots {
+        let root = Path::new(gpm_root);
+
+        if !root.exists() {
+            eprintln!("can not found the root folder '{}'", gpm_root);
+            continue;
+        }
+
+        match walker::walk_root(root) {
+            Ok(paths) => repositories.extend(
+                paths
+                    .into_iter()
+                    .map(|path| Repository::new(gpm_root, path)),
+            ),
+            Err(e) => eprintln!("can not walk the root folder '{}': {}", gpm_root, e),
+        }
+    }
+
+    repositories
+}
+
+#[cfg(test)]
+mod tests {
+    use std::path::Path;
+    use std::{env, fs};
+
+    use crate::repository::{self, Column, Repository};
+    use crate::testing;
+
+    #[test]
+    fn test_scan() {
+        let test_gpm_root = env::current_dir().unwrap().join("__test__").join("gpm");
+        let root = test_gpm_root.to_str().unwrap().to_string();
+
+        let r1 = repository::scan(&[root.clone(), "/path/to/not/exist".to_string()]);
+
+        assert_eq!(
+            r1,
+            vec![
+                Repository {
+                    root: root.clone(),
+                    path: test_gpm_root
+                        .join("github.com")
+                        .join("another_owner")
+                        .join("project"),
+                    host: "github.com".to_string(),
+                    owner: "another_owner".to_string(),
+                    name: "project".to_string(),
+                },
+                Repository {
+                    root: root.clone(),
+                    path: test_gpm_root
+                        .join("github.com")
+                        .join("axetroy")
+                        .join("gpm.rs"),
+                    host: "github.com".to_string(),
+                    owner: "axetroy".to_string(),
+                    name: "gpm.rs".to_string(),
+                },
+            ]
+        );
+    }
+
+    #[test]
+    fn test_locate() {
+        let roots = vec!["/home/gpm".to_string(), String::new()];
+
+        let repo = repository::locate(&roots, Path::new("/home/gpm/github.com/axetroy/gpm.rs"));
+
+        assert_eq!(repo.map(|r| r.root), Some("/home/gpm".to_string()));
+
+        for path in [
+            "/any/dir",
+            "/home/gpm",
+            "/home/gpm/github.com/axetroy",
+            "/home/gpm/github.com/axetroy/gpm.rs/src",
+            "/home/gpm/github.com/../axetroy/gpm.rs",
+            "/home/gpm/.gpm-trash/axetroy/gpm.rs",
+        ] {
+            assert_eq!(
+                repository::locate(&roots, Path::new(path)),
+                None,
+                "{}",
+                path
+            );
+        }
+    }
+
+    #[test]
+    fn test_column_parse() {
+        for column in Column::ALL {
+            assert_eq!(Column::parse(column.name()).unwrap(), column);
+        }
+
+        assert!(Column::parse("unknown").is_err());
+    }
+
+    #[test]
+    fn test_metadata() {
+        let repo = testing::init_repo("repository-metadata");
+
+        let r1 = Repository::new("/", repo.clone()).metadata(&[
+            Column::Branch,
+            Column::Status,
+            Column::Author,
+            Column::Origin,
+            Column::Size,
+        ]);
+
+        assert_eq!(r1.branch, Some("main".to_string()));
+        assert_eq!(r1.status, Some("clean".to_string()));
+        assert_eq!(r1.author, Some("gpm".to_string()));
+        assert_eq!(r1.date, None);
+        assert_eq!(r1.origin, None);
+        assert!(r1.size.unwrap() > 0);
+        assert_eq!(r1.value(Column::Origin), "-");
+        assert_eq!(r1.json(Column::Branch), serde_json::json!("main"));
+        assert_eq!(r1.json(Column::Origin), serde_json::Value::Null);
+
+        fs::remove_dir_all(repo).unwrap();
+    }
+}
@@ -0,0 +1,293 @@
+#![deny(warnings)]
+
+use crate::lock;
+use eyre::Report;
+use fs2::FileExt;
+use std::fs::{self, File, OpenOptions};
+use std::path::{Path, PathBuf};
+use std::process;
+use std::time::{SystemTime, UNIX_EPOCH};
+
+// The staging folder in the root, the hidden folders of root are not walked
+pub const STAGING_DIR: &str = ".gpm-staging";
+
+// The lock files of the clone destinations in the root
+pub const LOCKS_DIR: &str = ".gpm-locks";
+
+// The folder to clone into, it is renamed to the destination when the clone succeed.
+// The lock file beside it is locked until dropped, so the leftover of a killed process can be detected.
+pub struct Staging {
+    pub path: PathBuf,
+    lock_path: PathBuf,
+    _lock: File,
+}
+
+impl Staging {
+    pub fn new(root: &str, name: &str) -> Result<Staging, Report> {
+        let staging_dir = Path::new(root).join(STAGING_DIR);
+
+        fs::create_dir_all(&staging_dir)?;
+
+        let time = SystemTime::now()
+            .duration_since(UNIX_EPOCH)
+            .unwrap()
+            .as_secs();
+
+        let mut index = 0;
+
+        loop {
+            index += 1;
+
+            let path = staging_dir.join(format!("{}-{}-{}-{}", time, process::id(), index, name));
+            let lock_path = lock::lock_path(&path);
+
+            let lock = match OpenOptions::new()
+                .write(true)
+                .create_new(true)
+                .open(&lock_path)
+            {
+                Ok(file) => file,
+                Err(e) if e.kind() == std::io::ErrorKind::AlreadyExists => continue,
+                Err(e) => return Err(e.into()),
+            };
+
+            lock.lock_exclusive()?;
+
+            // the lock file may be cleaned by another process before locked
+            if !lock_path.exists() {
+                continue;
+            }
+
+            return Ok(Staging {
+                path,
+                lock_path,
+                _lock: lock,
+            });
+        }
+    }
+
+    // Move the cloned folder to the destination
+    pub fn finish(self, dest: &Path) -> Result<(), Report> {
+        if dest.exists() {
+            return Err(Report::msg(format!(
+                "the folder '{}' already exists",
+                dest.display()
+            )));
+        }
+
+        if let Some(parent) = dest.parent() {
+            fs::create_dir_all(parent)?;
+        }
+
+        fs::rename(&self.path, dest)?;
+
+        Ok(())
+    }
+}
+
+impl Drop for Staging {
+    fn drop(&mut self) {
+        if self.path.exists() {
+            fs::remove_dir_all(&self.path).ok();
+        }
+
+        fs::remove_file(&self.lock_path).ok();
+    }
+}
+
+// Lock the destination of clone, return None if another gpm is cloning into it
+pub fn lock_destination(root: &str, dest: &Path) -> Result<Option<lock::FileLock>, Report> {
+    let relative = dest.strip_prefix(root).unwrap_or(dest);
+
+    // eg. github.com%2Faxetroy%2Fgpm.rs.lock
+    let name = relative
+        .components()
+        .map(|c| c.as_os_str().to_string_lossy().replace('%', "%25"))
+        .collect::<Vec<String>>()
+        .join("%2F");
+
+    lock::try_lock(
+        &Path::new(root)
+            .join(LOCKS_DIR)
+            .join(format!("{}.lock", name)),
+    )
+}
+
+// The staging folders left by the killed or crashed process, they are removed by the next clone
+pub fn leftovers(root: &str) -> Result<Vec<PathBuf>, Report> {
+    let staging_dir = Path::new(root).join(STAGING_DIR);
+
+    if !staging_dir.exists() {
+        return Ok(vec![]);
+    }
+
+    Ok(fs::read_dir(&staging_dir)?
+        .flatten()
+        .map(|e| e.path())
+        .filter(|path| path.is_dir())
+        // the process is still cloning if the lock is held
+        .filter(|path| match File::open(lock::lock_path(path)) {
+            Ok(file) => file.try_lock_exclusive().is_ok(),
+            Err(_) => true,
+        })
+        .collect())
+}
+
+// Remove the staging folders left by the killed or crashed process, return the removed folders
+pub fn clean(root: &str) -> Result<Vec<PathBuf>, Report> {
+    let staging_dir = Path::new(root).join(STAGING_DIR);
+
+    if !staging_dir.exists() {
+        return Ok(vec![]);
+    }
+
+    let mut cleaned: Vec<PathBuf> = vec![];
+
+    for path in fs::read_dir(&staging_dir)?.flatten().map(|e| e.path()) {
+        if !path.is_dir() {
+            continue;
+        }
+
+        let lock_path = lock::lock_path(&path);
+
+        // the process is still cloning if the lock is held
+        let lock = match File::open(&lock_path) {
+            Ok(file) => {
+                if file.try_lock_exclusive().is_err() {
+                    continue;
+                }
+
+                Some(file)
+            }
+            Err(_) => None,
+        };
+
+        fs::remove_dir_all(&path)?;
+
+        if lock.is_some() {
+            fs::remove_file(&lock_path).ok();
+        }
+
+        cleaned.push(path);
+    }
+
+    // the lock files without the folder, the clone was killed before started
+    for path in fs::read_dir(&staging_dir)?.flatten().map(|e| e.path()) {
+        let dir = path
+            .to_str()
+            .and_then(|p| p.strip_suffix(".lock"))
+            .map(PathBuf::from);
+
+        if dir.is_some_and(|dir| !dir.exists()) {
+            if let Ok(file) = File::open(&path) {
+                if file.try_lock_exclusive().is_ok() {
+                    fs::remove_file(&path).ok();
+                }
+            }
+        }
+    }
+
+    Ok(cleaned)
+}
+
+#[cfg(test)]
+mod tests {
+    use std::fs;
+
+    use crate::staging::{self, Staging};
+    use crate::testing;
+
+    #[test]
+    fn test_staging_finish() {
+        let root = testing::temp_dir("staging-root");
+        let root_str = root.to_str().unwrap();
+
+        let dest = root.join("github.com").join("axetroy").join("gpm.rs");
+
+        let s1 = Staging::new(root_str, "gpm.rs").unwrap();
+
+        fs::create_dir_all(&s1.path).unwrap();
+        fs::write(s1.path.join("file"), "content").unwrap();
+
+        // the staging folder of running process is not cleaned
+        assert!(staging::clean(root_str).unwrap().is_empty());
+
+        s1.finish(&dest).unwrap();
+
+        assert_eq!(fs::read_to_string(dest.join("file")).unwrap(), "content");
+
+        let s2 = Staging::new(root_str, "gpm.rs").unwrap();
+
+        fs::create_dir_all(&s2.path).unwrap();
+
+        assert!(s2.finish(&dest).is_err());
+
+        // nothing left in staging folder
+        assert_eq!(
+            fs::read_dir(root.join(staging::STAGING_DIR))
+                .unwrap()
+                .count(),
+            0
+        );
+
+        fs::remove_dir_all(root).unwrap();
+    }
+
+    #[test]
+    fn test_lock_destination() {
+        let root = testing::temp_dir("staging-lock-root");
+        let root_str = root.to_str().unwrap();
+
+        let dest = root.join("github.com").join("axetroy").join("gpm.rs");
+
+        let l1 = staging::lock_destination(root_str, &dest).unwrap();
+
+        assert!(l1.is_some());
+        assert!(staging::lock_destination(root_str, &dest)
+            .unwrap()
+            .is_none());
+        assert!(root
+            .join(staging::LOCKS_DIR)
+            .join("github.com%2Faxetroy%2Fgpm.rs.lock")
+            .exists());
+
+        drop(l1);
+
+        assert!(staging::lock_destination(root_str, &dest)
+            .unwrap()
+            .is_some());
+
+        fs::remove_dir_all(root).unwrap();
+    }
+
+    #[test]
+    fn test_clean_leftover() {
+        let root = testing::temp_dir("staging-clean-root");
+        let root_str = root.to_str().unwrap();
+
+        // the leftover of killed process, no lock is held
+        let leftover = root.join(staging::STAGING_DIR).join("1-1-1-gpm.rs");
+
+        fs::create_dir_all(&leftover).unwrap();
+        fs::write(
+            root.join(staging::STAGING_DIR).join("1-1-1-gpm.rs.lock"),
+            "",
+        )
+        .unwrap();
+        fs::write(
+            root.join(staging::STAGING_DIR).join("1-1-2-gpm.rs.lock"),
+            "",
+        )
+        .unwrap();
+
+        assert_eq!(staging::clean(root_str).unwrap(), vec![leftover.clone()]);
+        assert_eq!(
+            fs::read_dir(root.join(staging::STAGING_DIR))
+                .unwrap()
+                .count(),
+            0
+        );
+
+        fs::remove_dir_all(root).unwrap();
+    }
+}
@@ -0,0 +1,281 @@
+#![deny(warnings)]
+
+use crate::format::Output;
+use crate::git;
+use crate::log;
+use crate::repository::Repository;
+use eyre::Report;
+use serde_json::json;
+use std::collections::{BTreeMap, BTreeSet};
+use std::path::Path;
+
+// The contribution of an author
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct Stats {
+    pub name: String,
+    pub email: String,
+    pub commits: usize,
+    pub added: usize,
+    pub removed: usize,
+    // the dates with commits. eg. 2023-01-01
+    pub days: BTreeSet<String>,
+}
+
+impl Stats {
+    fn merge(&mut self, other: &Stats) {
+        if self.name.is_empty() {
+            self.name = other.name.clone();
+            self.email = other.email.clone();
+        }
+
+        self.commits += other.commits;
+        self.added += other.added;
+        self.removed += other.removed;
+        self.days.extend(other.days.iter().cloned());
+    }
+}
+
+// Parse the output of `git log --numstat` with the header '\0name\0email\0date' of each commit
+fn parse(log: &str) -> Vec<Stats> {
+    let mut authors: BTreeMap<String, Stats> = BTreeMap::new();
+    let mut current: Option<String> = None;
+
+    for line in log.lines() {
+        if let Some(header) = line.strip_prefix('\0') {
+            let fields = header.splitn(3, '\0').collect::<Vec<&str>>();
+
+            if fields.len() < 3 {
+                continue;
+            }
+
+            let key = fields[1].to_lowercase();
+            let stats = authors.entry(key.clone()).or_insert_with(|| Stats {
+                name: fields[0].to_string(),
+                email: fields[1].to_string(),
+                ..Stats::default()
+            });
+
+            stats.commits += 1;
+            stats.days.insert(fields[2].to_string());
+
+            current = Some(key);
+        } else if let Some(key) = &current {
+            let mut fields = line.split('\t');
+
+            // the binary file is '-'
+            let (Some(added), Some(removed), Some(_)) =
+                (fields.next(), fields.next(), fields.next())
+            else {
+                continue;
+            };
+
+            let stats = authors.get_mut(key).unwrap();
+
+            stats.added += added.parse::<usize>().unwrap_or(0);
+            stats.removed += removed.parse::<usize>().unwrap_or(0);
+        }
+    }
+
+    let mut list = authors.into_values().collect::<Vec<Stats>>();
+
+    sort(&mut list);
+
+    list
+}
+
+fn sort(list: &mut [Stats]) {
+    list.sort_by(|a, b| b.commits.cmp(&a.commits).then_with(|| a.name.cmp(&b.name)));
+}
+
+// The contribution of each author in the repository, the .mailmap is respected
+pub fn collect(dir: &Path, options: &log::Options) -> Result<Vec<Stats>, Report> {
+    if !git::is_repository(dir) || git::rev_parse(dir, "HEAD").is_none() {
+        return Ok(vec![]);
+    }
+
+    let mut args: Vec<String> = vec![
+        "log".to_string(),
+        "--use-mailmap".to_string(),
+        "--numstat".to_string(),
+        "--date=short".to_string(),
+        "--format=%x00%aN%x00%aE%x00%ad".to_string(),
+    ];
+
+    args.extend(log::range_args(dir, options)?);
+
+    let log = git::output(dir, &args.iter().map(|s| s.as_str()).collect::<Vec<&str>>())?;
+
+    Ok(parse(&log))
+}
+
+// The total contribution of each author across repositories
+pub fn by_author(stats: &[(Repository, Vec<Stats>)]) -> Vec<(Stats, usize)> {
+    let mut authors: BTreeMap<String, (Stats, usize)> = BTreeMap::new();
+
+    for (_, list) in stats {
+        for item in list {
+            let entry = authors
+                .entry(item.email.to_lowercase())
+                .or_insert_with(|| (Stats::default(), 0));
+
+            entry.0.merge(item);
+            entry.1 += 1;
+        }
+    }
+
+    let mut list = authors.into_values().collect::<Vec<_>>();
+
+    list.sort_by(|a, b| {
+        b.0.commits
+            .cmp(&a.0.commits)
+            .then_with(|| a.0.name.cmp(&b.0.name))
+    });
+
+    list
+}
+
+// The total contribution of all authors in the repository
+pub fn total(list: &[Stats]) -> Stats {
+    let mut total = Stats::default();
+
+    for item in list {
+        total.merge(item);
+    }
+
+    total
+}
+
+// The structured output grouped by author
+pub fn author_output(stats: &[(Repository, Vec<Stats>)]) -> Output {
+    let mut output = Output::new(
+        "author-stats",
+        &[
+            "name", "email", "commits", "added", "removed", "days", "repos",
+        ],
+    );
+
+    for (item, repos) in by_author(stats) {
+        output.push(vec![
+            json!(item.name),
+            json!(item.email),
+            json!(item.commits),
+            json!(item.added),
+            json!(item.removed),
+            json!(item.days.len()),
+            json!(repos),
+        ]);
+    }
+
+    output
+}
+
+// The structured output grouped by repository
+pub fn repository_output(stats: &[(Repository, Vec<Stats>)]) -> Output {
+    let fields = Repository::FIELDS
+        .into_iter()
+        .chain(["commits", "added", "removed", "days", "authors"])
+        .collect::<Vec<&str>>();
+
+    let mut output = Output::new("repository-stats", &fields).with_tree_levels(3);
+
+    let mut items = stats
+        .iter()
+        .map(|(repo, list)| (repo, total(list), list.len()))
+        .collect::<Vec<_>>();
+
+    items.sort_by_key(|(_, item, _)| std::cmp::Reverse(item.commits));
+
+    for (repo, item, authors) in items {
+        let mut record = repo.fields();
+
+        record.extend([
+            json!(item.commits),
+            json!(item.added),
+            json!(item.removed),
+            json!(item.days.len()),
+            json!(authors),
+        ]);
+
+        output.push(record);
+    }
+
+    output
+}
+
+#[cfg(test)]
+mod tests {
+    use std::fs;
+
+    use crate::log::Options;
+    use crate::repository::Repository;
+    use crate::stats;
+    use crate::testing;
+
+    #[test]
+    fn test_parse() {
+        let log = "\0alice\0alice@example.com\x002023-01-01\n\n1\t2\ta.txt\n-\t-\timage.png\n\0bob\0bob@example.com\x002023-01-02\n\n3\t0\tb.txt\n\0Alice\0ALICE@example.com\x002023-01-03\n\n";
+
+        let list = stats::parse(log);
+
+        assert_eq!(list.len(), 2);
+        assert_eq!(list[0].name, "alice");
+        assert_eq!(list[0].commits, 2);
+        assert_eq!((list[0].added, list[0].removed), (1, 2));
+        assert_eq!(list[0].days.len(), 2);
+        assert_eq!(list[1].name, "bob");
+        assert_eq!(list[1].added, 3);
+    }
+
+    #[test]
+    fn test_collect_with_mailmap() {
+        let repo = testing::init_repo("stats-repo");
+
+        fs::write(repo.join("file"), "1\n2\n3\n").unwrap();
+        testing::git(&repo, &["add", "-A"]);
+        testing::git(
+            &repo,
+            &[
+                "-c",
+                "user.name=old",
+                "-c",
+                "user.email=old@example.com",
+                "commit",
+                "-q",
+                "--author=old <old@example.com>",
+                "-m",
+                "old",
+            ],
+        );
+
+        let r1 = stats::collect(&repo, &Options::default()).unwrap();
+
+        assert_eq!(r1.len(), 2);
+
+        fs::write(
+            repo.join(".mailmap"),
+            "gpm <gpm@example.com> old <old@example.com>\n",
+        )
+        .unwrap();
+
+        let r2 = stats::collect(&repo, &Options::default()).unwrap();
+
+        assert_eq!(r2.len(), 1);
+        assert_eq!(r2[0].name, "gpm");
+        assert_eq!(r2[0].commits, 2);
+        // the files 'init' and 'file'
+        assert_eq!(r2[0].added, 4);
+
+        let all = vec![
+            (Repository::new("/", repo.clone()), r2.clone()),
+            (Repository::new("/", repo.clone()), r2),
+        ];
+
+        let authors = stats::by_author(&all);
+
+        assert_eq!(authors.len(), 1);
+        assert_eq!(authors[0].0.commits, 4);
+        assert_eq!(authors[0].1, 2);
+
+        fs::remove_dir_all(repo).unwrap();
+    }
+}
@@ -0,0 +1,215 @@
+#![deny(warnings)]
+
+use crate::format::Output;
+use crate::git;
+use crate::repository::Repository;
+use serde_json::json;
+use std::path::Path;
+
+// The state of repository which may need attention before leaving
+#[derive(Debug, Default, Clone, PartialEq)]
+pub struct Status {
+    pub changes: u32,
+    pub unpushed: u32,
+    pub stashes: u32,
+    pub detached: bool,
+    pub error: Option<String>,
+}
+
+impl Status {
+    pub fn check(dir: &Path) -> Status {
+        if !git::is_repository(dir) {
+            return Status {
+                error: Some("not a git repository".to_string()),
+                ..Status::default()
+            };
+        }
+
+        let result = (|| -> Result<Status, eyre::Report> {
+            Ok(Status {
+                changes: git::output(dir, &["status", "--porcelain"])?
+                    .lines()
+                    .count() as u32,
+                unpushed: git::unpushed_count(dir)?.unwrap_or(0),
+                stashes: git::stash_count(dir)?,
+                detached: git::current_branch(dir)?.is_none(),
+                error: None,
+            })
+        })();
+
+        result.unwrap_or_else(|e| Status {
+            error: Some(e.to_string()),
+            ..Status::default()
+        })
+    }
+
+    pub fn needs_attention(&self) -> bool {
+        self.changes > 0
+            || self.unpushed > 0
+            || self.stashes > 0
+            || self.detached
+            || self.error.is_some()
+    }
+
+    // The reasons of attention for printing. eg. 2 changes, 1 unpushed
+    pub fn reasons(&self) -> Vec<String> {
+        let mut reasons: Vec<String> = vec![];
+
+        let plural = |n: u32, word: &str, words: &str| {
+            format!("{} {}", n, if n == 1 { word } else { words })
+        };
+
+        if self.changes > 0 {
+            reasons.push(plural(self.changes, "change", "changes"));
+        }
+
+        if self.unpushed > 0 {
+            reasons.push(plural(self.unpushed, "unpushed commit", "unpushed commits"));
+        }
+
+        if self.stashes > 0 {
+            reasons.push(plural(self.stashes, "stash", "stashes"));
+        }
+
+        if self.detached {
+            reasons.push("detached HEAD".to_string());
+        }
+
+        if let Some(e) = &self.error {
+            reasons.push(format!("error: {}", e));
+        }
+
+        reasons
+    }
+}
+
+// The structured output of the repositories need attention
+pub fn output(repositories: &[Repository], statuses: &[Status]) -> Output {
+    let fields = Repository::FIELDS
+        .into_iter()
+        .chain(["changes", "unpushed", "stashes", "detached", "error"])
+        .collect::<Vec<&str>>();
+
+    let mut output = Output::new("status", &fields).with_tree_levels(3);
+
+    for (repo, status) in repositories.iter().zip(statuses.iter()) {
+        let mut record = repo.fields();
+
+        record.extend([
+            json!(status.changes),
+            json!(status.unpushed),
+            json!(status.stashes),
+            json!(status.detached),
+            json!(status.error),
+        ]);
+
+        output.push(record);
+    }
+
+    output
+}
+
+// The summary line. eg. 2 of 10 repositories need attention: 1 with changes, 1 with stashes
+pub fn summary(total: usize, statuses: &[Status]) -> String {
+    let count = |f: fn(&Status) -> bool| statuses.iter().filter(|s| f(s)).count();
+
+    let details = [
+        (count(|s| s.changes > 0), "with changes"),
+        (count(|s| s.unpushed > 0), "with unpushed commits"),
+        (count(|s| s.stashes > 0), "with stashes"),
+        (count(|s| s.detached), "detached"),
+        (count(|s| s.error.is_some()), "with errors"),
+    ]
+    .into_iter()
+    .filter(|(n, _)| *n > 0)
+    .map(|(n, text)| format!("{} {}", n, text))
+    .collect::<Vec<String>>();
+
+    let attention = count(|s| s.needs_attention());
+
+    if details.is_empty() {
+        format!("All {} repositories are clean", total)
+    } else {
+        format!(
+            "{} of {} repositories need attention: {}",
+            attention,
+            total,
+            details.join(", ")
+        )
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::fs;
+
+    use crate::status::{self, Status};
+    use crate::testing;
+
+    #[test]
+    fn test_status_check() {
+        let remote = testing::init_repo("status-remote");
+        let repo = testing::clone_repo(&remote, "status-repo");
+
+        let r1 = Status::check(&repo);
+
+        assert_eq!(r1, Status::default());
+        assert!(!r1.needs_attention());
+
+        testing::commit(&repo, "local");
+        fs::write(repo.join("a"), "").unwrap();
+        fs::write(repo.join("b"), "").unwrap();
+        testing::git(&repo, &["stash", "-u", "-q"]);
+        fs::write(repo.join("c"), "").unwrap();
+
+        let r2 = Status::check(&repo);
+
+        assert_eq!(
+            r2,
+            Status {
+                changes: 1,
+                unpushed: 1,
+                stashes: 1,
+                detached: false,
+                error: None,
+            }
+        );
+        assert_eq!(
+            r2.reasons(),
+            vec!["1 change", "1 unpushed commit", "1 stash"]
+        );
+
+        testing::git(&repo, &["checkout", "-q", "--detach"]);
+
+        assert!(Status::check(&repo).detached);
+
+        let not_repo = testing::temp_dir("status-not-repo");
+
+        assert!(Status::check(&not_repo).error.is_some());
+
+        // the repository which can not be inspected needs attention too
+        let broken = testing::init_repo("status-broken");
+
+        fs::write(broken.join(".git").join("HEAD"), "broken").unwrap();
+
+        let r3 = Status::check(&broken);
+
+        assert!(r3.needs_attention());
+        assert!(r3.reasons()[0].starts_with("error: "));
+        assert_eq!(
+            status::summary(2, &[r1.clone(), r3]),
+            "1 of 2 repositories need attention: 1 with errors"
+        );
+
+        assert_eq!(
+            status::summary(3, &[r1.clone(), r2.clone()]),
+            "1 of 3 repositories need attention: 1 with changes, 1 with unpushed commits, 1 with stashes"
+        );
+        assert_eq!(status::summary(1, &[r1]), "All 1 repositories are clean");
+
+        fs::remove_dir_all(remote).unwrap();
+        fs::remove_dir_all(repo).unwrap();
+        fs::remove_dir_all(not_repo).unwrap();
+        fs::remove_dir_all(broken).unwrap();
+    }
+}
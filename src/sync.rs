@@ -0,0 +1,301 @@
+#![deny(warnings)]
+
+use crate::format::Output;
+use crate::git;
+use crate::network;
+use crate::news;
+use crate::parallel;
+use crate::progress::Progress;
+use crate::repository::Repository;
+use serde_json::json;
+use std::path::Path;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Action {
+    Fetch,
+    // fast-forward the current branch to upstream after fetch
+    Pull,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub enum SyncResult {
+    Updated(String),
+    UpToDate,
+    Skipped(String),
+    Failed(String),
+}
+
+#[derive(Debug, Clone)]
+pub struct Options {
+    pub action: Action,
+    pub jobs: usize,
+    // the max count of repositories of the same host to sync at the same time
+    pub host_jobs: usize,
+    pub network: network::Options,
+}
+
+impl Action {
+    fn verb(&self) -> &'static str {
+        match self {
+            Action::Fetch => "fetching",
+            Action::Pull => "pulling",
+        }
+    }
+}
+
+impl SyncResult {
+    pub fn name(&self) -> &'static str {
+        match self {
+            SyncResult::Updated(_) => "updated",
+            SyncResult::UpToDate => "up-to-date",
+            SyncResult::Skipped(_) => "skipped",
+            SyncResult::Failed(_) => "failed",
+        }
+    }
+
+    pub fn detail(&self) -> Option<&str> {
+        match self {
+            SyncResult::Updated(s) | SyncResult::Skipped(s) | SyncResult::Failed(s) => Some(s),
+            SyncResult::UpToDate => None,
+        }
+    }
+}
+
+// Fetch all remotes of the repository
+pub fn fetch(dir: &Path, options: &network::Options) -> SyncResult {
+    if !git::is_repository(dir) {
+        return SyncResult::Skipped("not a git repository".to_string());
+    }
+
+    match git::output(dir, &["remote"]) {
+        Ok(remotes) if remotes.is_empty() => {
+            return SyncResult::Skipped("no remote".to_string());
+        }
+        Ok(_) => {}
+        Err(e) => return SyncResult::Failed(e.to_string()),
+    }
+
+    let before = git::remote_refs(dir).unwrap_or_default();
+
+    if let Err(e) = network::output(
+        dir,
+        &["fetch", "--all", "--prune", "--tags", "--quiet"],
+        options,
+    ) {
+        return SyncResult::Failed(e.to_string());
+    }
+
+    let after = git::remote_refs(dir).unwrap_or_default();
+
+    let changes = news::diff(&before, &after);
+    let changed = changes.len();
+
+    // keep the changes for the digest of `gpm news`
+    if let Err(e) = news::record(dir, changes) {
+        return SyncResult::Failed(format!("can not record the news: {}", e));
+    }
+
+    if changed == 0 {
+        SyncResult::UpToDate
+    } else {
+        SyncResult::Updated(format!(
+            "{} {} changed",
+            changed,
+            if changed == 1 { "ref" } else { "refs" }
+        ))
+    }
+}
+
+// Fetch and fast-forward the current branch, skip if it can not be fast-forwarded safely
+pub fn pull(dir: &Path, options: &network::Options) -> SyncResult {
+    if !git::is_repository(dir) {
+        return SyncResult::Skipped("not a git repository".to_string());
+    }
+
+    match git::current_branch(dir) {
+        Ok(Some(_)) => {}
+        Ok(None) => return SyncResult::Skipped("detached HEAD".to_string()),
+        Err(e) => return SyncResult::Failed(e.to_string()),
+    }
+
+    match git::is_dirty(dir) {
+        Ok(false) => {}
+        Ok(true) => return SyncResult::Skipped("uncommitted changes".to_string()),
+        Err(e) => return SyncResult::Failed(e.to_string()),
+    }
+
+    if git::upstream(dir).is_none() {
+        return SyncResult::Skipped("no upstream".to_string());
+    }
+
+    if let SyncResult::Failed(e) = fetch(dir, options) {
+        return SyncResult::Failed(e);
+    }
+
+    let before = git::rev_parse(dir, "HEAD");
+
+    if let Err(e) = git::output(dir, &["merge", "--ff-only", "--quiet", "@{upstream}"]) {
+        return SyncResult::Failed(e.to_string());
+    }
+
+    let after = git::rev_parse(dir, "HEAD");
+
+    match (before, after) {
+        (Some(before), Some(after)) if before != after => {
+            SyncResult::Updated(format!("{}..{}", &before[..7], &after[..7]))
+        }
+        (None, Some(after)) => SyncResult::Updated(format!("..{}", &after[..7])),
+        _ => SyncResult::UpToDate,
+    }
+}
+
+// Sync the repositories with a pool of workers and live progress
+pub fn run(repositories: &[Repository], options: &Options) -> Vec<SyncResult> {
+    let progress = Progress::new();
+    let host = |repo: &Repository| repo.host.clone();
+
+    parallel::map_keyed(
+        repositories,
+        options.jobs,
+        options.host_jobs,
+        host,
+        |repo| {
+            let id = progress.start(&format!("{} {}", options.action.verb(), repo.path_str()));
+
+            let result = match options.action {
+                Action::Fetch => fetch(&repo.path, &options.network),
+                Action::Pull => pull(&repo.path, &options.network),
+            };
+
+            let message = match result.detail() {
+                Some(detail) => format!("{:10} {} ({})", result.name(), repo.path_str(), detail),
+                None => format!("{:10} {}", result.name(), repo.path_str()),
+            };
+
+            progress.finish(id, &message);
+
+            result
+        },
+    )
+}
+
+// The structured output of results
+pub fn output(repositories: &[Repository], results: &[SyncResult]) -> Output {
+    let fields = Repository::FIELDS
+        .into_iter()
+        .chain(["result", "detail"])
+        .collect::<Vec<&str>>();
+
+    let mut output = Output::new("sync", &fields).with_tree_levels(3);
+
+    for (repo, result) in repositories.iter().zip(results.iter()) {
+        let mut record = repo.fields();
+
+        record.extend([json!(result.name()), json!(result.detail())]);
+
+        output.push(record);
+    }
+
+    output
+}
+
+// The summary line. eg. 2 updated, 10 up-to-date, 1 skipped, 0 failed
+pub fn summary(results: &[SyncResult]) -> String {
+    ["updated", "up-to-date", "skipped", "failed"]
+        .iter()
+        .map(|name| {
+            let count = results.iter().filter(|r| r.name() == *name).count();
+            format!("{} {}", count, name)
+        })
+        .collect::<Vec<String>>()
+        .join(", ")
+}
+
+#[cfg(test)]
+mod tests {
+    use std::fs;
+
+    use crate::network;
+    use crate::repository::Repository;
+    use crate::sync::{self, Action, Options, SyncResult};
+    use crate::testing;
+
+    #[test]
+    fn test_fetch_and_pull() {
+        let remote = testing::init_bare_repo("sync-remote");
+        let writer = testing::clone_repo(&remote, "sync-writer");
+        let repo = testing::clone_repo(&remote, "sync-repo");
+        let network = network::Options::default();
+
+        assert_eq!(sync::fetch(&repo, &network), SyncResult::UpToDate);
+        assert_eq!(sync::pull(&repo, &network), SyncResult::UpToDate);
+
+        testing::commit(&writer, "remote");
+        testing::git(&writer, &["push", "-q", "origin", "main"]);
+
+        assert_eq!(
+            sync::fetch(&repo, &network),
+            SyncResult::Updated("1 ref changed".to_string())
+        );
+        assert!(matches!(
+            sync::pull(&repo, &network),
+            SyncResult::Updated(_)
+        ));
+        assert!(repo.join("remote").exists());
+
+        fs::write(repo.join("dirty"), "").unwrap();
+
+        assert_eq!(
+            sync::pull(&repo, &network),
+            SyncResult::Skipped("uncommitted changes".to_string())
+        );
+
+        fs::remove_file(repo.join("dirty")).unwrap();
+        testing::git(&repo, &["checkout", "-q", "--detach"]);
+
+        assert_eq!(
+            sync::pull(&repo, &network),
+            SyncResult::Skipped("detached HEAD".to_string())
+        );
+
+        testing::git(&repo, &["checkout", "-q", "main"]);
+        testing::commit(&repo, "diverged");
+        testing::commit(&writer, "another");
+        testing::git(&writer, &["push", "-q", "origin", "main"]);
+
+        assert!(matches!(sync::pull(&repo, &network), SyncResult::Failed(_)));
+
+        let repositories = vec![
+            Repository::new("/", repo.clone()),
+            Repository::new("/", writer.clone()),
+        ];
+
+        let r1 = sync::run(
+            &repositories,
+            &Options {
+                action: Action::Fetch,
+                jobs: 2,
+                host_jobs: 1,
+                network: network.clone(),
+            },
+        );
+
+        assert_eq!(r1, vec![SyncResult::UpToDate, SyncResult::UpToDate]);
+        assert_eq!(
+            sync::summary(&r1),
+            "0 updated, 2 up-to-date, 0 skipped, 0 failed"
+        );
+
+        let not_repo = testing::temp_dir("sync-not-repo");
+
+        assert!(matches!(
+            sync::fetch(&not_repo, &network),
+            SyncResult::Skipped(_)
+        ));
+
+        fs::remove_dir_all(remote).unwrap();
+        fs::remove_dir_all(writer).unwrap();
+        fs::remove_dir_all(repo).unwrap();
+        fs::remove_dir_all(not_repo).unwrap();
+    }
+}
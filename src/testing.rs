@@ -0,0 +1,97 @@
+#![deny(warnings)]
+
+use std::env;
+use std::fs;
+use std::path::{Path, PathBuf};
+use std::process::Command as ChildProcess;
+
+// Run git with the identity of test
+pub fn git(dir: &Path, args: &[&str]) {
+    let status = ChildProcess::new("git")
+        .arg("-C")
+        .arg(dir)
+        .args(args)
+        .env("GIT_AUTHOR_NAME", "gpm")
+        .env("GIT_AUTHOR_EMAIL", "gpm@example.com")
+        .env("GIT_COMMITTER_NAME", "gpm")
+        .env("GIT_COMMITTER_EMAIL", "gpm@example.com")
+        .output()
+        .expect("can not run git");
+
+    assert!(
+        status.status.success(),
+        "git {:?} fail: {}",
+        args,
+        String::from_utf8_lossy(&status.stderr)
+    );
+}
+
+// A clean folder in the temp dir for the test
+pub fn temp_dir(name: &str) -> PathBuf {
+    let dir = env::temp_dir().join(format!("gpm-test-{}-{}", std::process::id(), name));
+
+    if dir.exists() {
+        fs::remove_dir_all(&dir).unwrap();
+    }
+
+    fs::create_dir_all(&dir).unwrap();
+
+    dir
+}
+
+// Create a commit with a file named by the message
+pub fn commit(repo: &Path, message: &str) {
+    fs::write(repo.join(message), message).unwrap();
+    git(repo, &["add", "-A"]);
+    git(repo, &["commit", "-q", "-m", message]);
+}
+
+// Init a repository on branch main with one commit
+pub fn init_repo(name: &str) -> PathBuf {
+    let repo = temp_dir(name);
+
+    git(&repo, &["init", "-q", "-b", "main"]);
+    commit(&repo, "init");
+
+    repo
+}
+
+// Init a bare repository with one commit on branch main
+pub fn init_bare_repo(name: &str) -> PathBuf {
+    let source = init_repo(&format!("{}-source", name));
+    let repo = temp_dir(name);
+
+    git(
+        &repo,
+        &[
+            "clone",
+            "-q",
+            "--bare",
+            source.to_str().unwrap(),
+            repo.to_str().unwrap(),
+        ],
+    );
+
+    fs::remove_dir_all(source).unwrap();
+
+    repo
+}
+
+// Clone the repository into a new temp folder
+pub fn clone_repo(remote: &Path, name: &str) -> PathBuf {
+    let repo = temp_dir(name);
+
+    git(
+        &repo,
+        &[
+            "clone",
+            "-q",
+            remote.to_str().unwrap(),
+            repo.to_str().unwrap(),
+        ],
+    );
+    git(&repo, &["config", "user.name", "gpm"]);
+    git(&repo, &["config", "user.email", "gpm@example.com"]);
+
+    repo
+}
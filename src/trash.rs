@@ -0,0 +1,226 @@
+#![deny(warnings)]
+
+use crate::format::Output;
+use crate::git;
+use eyre::Report;
+use serde::{Deserialize, Serialize};
+use serde_json::json;
+use std::fs;
+use std::path::{Path, PathBuf};
+use std::time::{SystemTime, UNIX_EPOCH};
+
+// The trash folder in the root, the hidden folders of root are not walked
+pub const TRASH_DIR: &str = ".gpm-trash";
+
+// The metadata of the removed folder
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq)]
+pub struct Meta {
+    // the original path
+    pub path: String,
+    // the url of origin
+    pub remote: Option<String>,
+    // the unix time of removing
+    pub time: u64,
+}
+
+// The folder in trash, the removed folder is kept in `<root>/.gpm-trash/<id>/data`
+#[derive(Debug, Clone, PartialEq)]
+pub struct Entry {
+    pub id: String,
+    pub root: String,
+    pub dir: PathBuf,
+    pub meta: Meta,
+}
+
+fn now() -> u64 {
+    SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .unwrap()
+        .as_secs()
+}
+
+impl Entry {
+    fn data(&self) -> PathBuf {
+        self.dir.join("data")
+    }
+
+    fn read(root: &str, dir: PathBuf) -> Result<Entry, Report> {
+        let meta: Meta = serde_json::from_str(&fs::read_to_string(dir.join("meta.json"))?)?;
+
+        Ok(Entry {
+            id: dir
+                .file_name()
+                .map(|n| n.to_string_lossy().to_string())
+                .unwrap_or_default(),
+            root: root.to_string(),
+            dir,
+            meta,
+        })
+    }
+}
+
+// Move the folder into the trash of root, it is a rename because the trash is in the same root
+pub fn trash(root: &str, path: &Path) -> Result<Entry, Report> {
+    let remote = if git::is_repository(path) {
+        git::remote_url(path, "origin")
+    } else {
+        None
+    };
+
+    let time = now();
+    let name = path
+        .file_name()
+        .map(|n| n.to_string_lossy().to_string())
+        .unwrap_or_default();
+
+    let trash_dir = Path::new(root).join(TRASH_DIR);
+
+    let mut id = format!("{}-{}", time, name);
+    let mut index = 1;
+
+    while trash_dir.join(&id).exists() {
+        index += 1;
+        id = format!("{}-{}-{}", time, name, index);
+    }
+
+    let dir = trash_dir.join(&id);
+
+    fs::create_dir_all(&dir)?;
+
+    let meta = Meta {
+        path: path.to_string_lossy().to_string(),
+        remote,
+        time,
+    };
+
+    fs::write(dir.join("meta.json"), serde_json::to_string(&meta)?)?;
+
+    let entry = Entry {
+        id,
+        root: root.to_string(),
+        dir,
+        meta,
+    };
+
+    if let Err(e) = fs::rename(path, entry.data()) {
+        fs::remove_dir_all(&entry.dir).ok();
+        return Err(e.into());
+    }
+
+    Ok(entry)
+}
+
+// The folders in the trash of roots, the newest first
+pub fn list(roots: &[String]) -> Vec<Entry> {
+    let mut entries: Vec<Entry> = vec![];
+
+    for root in roots {
+        let Ok(dirs) = fs::read_dir(Path::new(root).join(TRASH_DIR)) else {
+            continue;
+        };
+
+        for dir in dirs.flatten().map(|d| d.path()).filter(|d| d.is_dir()) {
+            match Entry::read(root, dir.clone()) {
+                Ok(entry) => entries.push(entry),
+                Err(e) => eprintln!("can not read the trash '{}': {}", dir.display(), e),
+            }
+        }
+    }
+
+    entries.sort_by(|a, b| b.meta.time.cmp(&a.meta.time).then(b.id.cmp(&a.id)));
+
+    entries
+}
+
+// Move the folder back to the original path
+pub fn restore(entry: &Entry) -> Result<PathBuf, Report> {
+    let path = PathBuf::from(&entry.meta.path);
+
+    if path.exists() {
+        return Err(Report::msg(format!(
+            "can not restore '{}', the folder already exists",
+            entry.meta.path
+        )));
+    }
+
+    if let Some(parent) = path.parent() {
+        fs::create_dir_all(parent)?;
+    }
+
+    fs::rename(entry.data(), &path)?;
+    fs::remove_dir_all(&entry.dir)?;
+
+    Ok(path)
+}
+
+// Remove the folder in trash permanently
+pub fn purge(entry: &Entry) -> Result<(), Report> {
+    Ok(fs::remove_dir_all(&entry.dir)?)
+}
+
+// The structured output of the folders in trash
+pub fn output(entries: &[Entry]) -> Output {
+    let mut output = Output::new("trash", &["id", "root", "path", "remote", "time"]);
+
+    for entry in entries {
+        output.push(vec![
+            json!(entry.id),
+            json!(entry.root),
+            json!(entry.meta.path),
+            json!(entry.meta.remote),
+            json!(entry.meta.time),
+        ]);
+    }
+
+    output
+}
+
+#[cfg(test)]
+mod tests {
+    use std::fs;
+
+    use crate::testing;
+    use crate::trash;
+
+    #[test]
+    fn test_trash_and_restore() {
+        let root = testing::temp_dir("trash-root");
+        let roots = vec![root.to_str().unwrap().to_string()];
+        let root_str = &roots[0];
+
+        let path = root.join("github.com").join("axetroy").join("gpm.rs");
+
+        fs::create_dir_all(&path).unwrap();
+        fs::write(path.join("file"), "content").unwrap();
+
+        let r1 = trash::trash(root_str, &path).unwrap();
+
+        assert!(!path.exists());
+        assert!(r1.id.ends_with("-gpm.rs"));
+        assert_eq!(r1.meta.remote, None);
+
+        fs::create_dir_all(&path).unwrap();
+
+        let r2 = trash::trash(root_str, &path).unwrap();
+
+        assert_ne!(r1.id, r2.id);
+
+        let entries = trash::list(&roots);
+
+        assert_eq!(entries.len(), 2);
+        assert_eq!(entries[0].meta.path, path.to_str().unwrap());
+
+        // restore the first one
+        assert_eq!(trash::restore(&r1).unwrap(), path);
+        assert_eq!(fs::read_to_string(path.join("file")).unwrap(), "content");
+
+        // the original path is occupied
+        assert!(trash::restore(&r2).is_err());
+
+        trash::purge(&r2).unwrap();
+
+        assert!(trash::list(&roots).is_empty());
+
+        fs::remove_dir_all(root).unwrap();
+    }
+}
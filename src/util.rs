@@ -1,4 +1,8 @@
-use std::path::PathBuf;
+use eyre::Report;
+use std::fs;
+use std::io;
+use std::path::{Path, PathBuf};
+use std::time::Duration;
 
 /// Find an available path that does not exist in your system
 pub(crate) fn find_available_path(mut filepath: PathBuf) -> PathBuf {
@@ -25,11 +29,73 @@ pub(crate) fn find_available_path(mut filepath: PathBuf) -> PathBuf {
     filepath
 }
 
+/// The total size in bytes of the files in the folder, symbolic links are not followed
+pub(crate) fn dir_size(dir: &Path) -> io::Result<u64> {
+    let mut size: u64 = 0;
+
+    for entry in fs::read_dir(dir)?.flatten() {
+        let metadata = entry.path().symlink_metadata()?;
+
+        if metadata.is_dir() {
+            size += dir_size(&entry.path())?;
+        } else {
+            size += metadata.len();
+        }
+    }
+
+    Ok(size)
+}
+
+/// Format the bytes for human reading. eg. 1.5 MiB
+pub(crate) fn format_size(bytes: u64) -> String {
+    let units = ["B", "KiB", "MiB", "GiB", "TiB"];
+    let mut size = bytes as f64;
+    let mut unit = 0;
+
+    while size >= 1024.0 && unit < units.len() - 1 {
+        size /= 1024.0;
+        unit += 1;
+    }
+
+    if unit == 0 {
+        format!("{} {}", bytes, units[unit])
+    } else {
+        format!("{:.1} {}", size, units[unit])
+    }
+}
+
+/// Parse the duration with unit, the unit can be s, m, h, d, w, y. eg. 90d
+pub(crate) fn parse_duration(text: &str) -> Result<Duration, Report> {
+    let invalid = || Report::msg(format!("invalid duration '{}', eg. 30m, 12h, 90d", text));
+
+    let index = text
+        .find(|c: char| !c.is_ascii_digit())
+        .ok_or_else(invalid)?;
+
+    let (number, unit) = text.split_at(index);
+
+    let number: u64 = number.parse().map_err(|_| invalid())?;
+
+    let seconds = match unit {
+        "s" => 1,
+        "m" => 60,
+        "h" => 60 * 60,
+        "d" => 24 * 60 * 60,
+        "w" => 7 * 24 * 60 * 60,
+        "y" => 365 * 24 * 60 * 60,
+        _ => return Err(invalid()),
+    };
+
+    Ok(Duration::from_secs(
+        number.checked_mul(seconds).ok_or_else(invalid)?,
+    ))
+}
+
 #[cfg(test)]
 mod tests {
-    use std::env;
+    use std::{env, fs};
 
-    use crate::util;
+    use crate::{testing, util};
 
     #[test]
     fn test_find_available_path() {
@@ -61,4 +127,41 @@ mod tests {
                 .to_str()
         );
     }
+
+    #[test]
+    fn test_dir_size() {
+        let dir = testing::temp_dir("util-dir-size");
+
+        fs::create_dir_all(dir.join("a").join("b")).unwrap();
+        fs::write(dir.join("file"), "hello").unwrap();
+        fs::write(dir.join("a").join("b").join("file"), "world!").unwrap();
+
+        let r1 = util::dir_size(&dir).unwrap();
+
+        assert_eq!(r1, 11);
+
+        fs::remove_dir_all(dir).unwrap();
+    }
+
+    #[test]
+    fn test_format_size() {
+        assert_eq!(util::format_size(0), "0 B");
+        assert_eq!(util::format_size(1023), "1023 B");
+        assert_eq!(util::format_size(1536), "1.5 KiB");
+        assert_eq!(util::format_size(5 * 1024 * 1024), "5.0 MiB");
+    }
+
+    #[test]
+    fn test_parse_duration() {
+        assert_eq!(util::parse_duration("30s").unwrap().as_secs(), 30);
+        assert_eq!(util::parse_duration("2m").unwrap().as_secs(), 120);
+        assert_eq!(util::parse_duration("90d").unwrap().as_secs(), 90 * 86400);
+        assert_eq!(util::parse_duration("1w").unwrap().as_secs(), 7 * 86400);
+
+        assert!(util::parse_duration("90").is_err());
+        assert!(util::parse_duration("d").is_err());
+        assert!(util::parse_duration("1x").is_err());
+        assert!(util::parse_duration("").is_err());
+        assert!(util::parse_duration("99999999999999y").is_err());
+    }
 }
@@ -2,7 +2,7 @@
 
 use core::result::Result;
 use eyre::Report;
-use std::path::Path;
+use std::path::{Path, PathBuf};
 use std::process::Command as ChildProcess;
 use which::which;
 
@@ -16,9 +16,9 @@ static DEFAULT_CODE_PATH: &str = "/usr/share/code/bin/code";
 #[cfg(target_os = "windows")]
 static DEFAULT_CODE_PATH: &str = "C:\\Program Files\\Microsoft VS Code";
 
-// Open a path in file explorer
-pub fn open(folder: &Path) -> Result<(), Report> {
-    let open_command = match which("code") {
+// Find the command of Visual Studio Code
+pub fn find() -> Result<PathBuf, Report> {
+    match which("code") {
         Ok(p) => Ok(p),
         Err(_) => {
             // Try to find VS Code in the default install location
@@ -30,7 +30,12 @@ pub fn open(folder: &Path) -> Result<(), Report> {
                 Err(Report::msg("Visual Studio Code is not installed"))
             }
         }
-    }?;
+    }
+}
+
+// Open a path in Visual Studio Code
+pub fn open(folder: &Path) -> Result<(), Report> {
+    let open_command = find()?;
 
     ChildProcess::new(open_command)
         .arg(folder.as_os_str().to_str().unwrap())
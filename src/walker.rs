@@ -11,6 +11,14 @@ pub fn walk_root(dir: &Path) -> io::Result<Vec<PathBuf>> {
             continue;
         }
 
+        // the hidden folders are used by gpm. eg. the trash
+        if source
+            .file_name()
+            .is_some_and(|n| n.to_string_lossy().starts_with('.'))
+        {
+            continue;
+        }
+
         let owner_entry = fs::read_dir(source)?;
 
         for owner in owner_entry.flatten().map(|s| s.path()) {